@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/* Shape of an eventually periodic sequence x_0, x_1 = f(x_0), ...
+ * x_{prefix_len} is the first state that repeats, and it repeats every `period` steps. */
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Cycle {
+    pub prefix_len: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    /* Smallest step with the same state as step n */
+    pub fn reduce(&self, n: u64) -> usize {
+        let prefix_len = self.prefix_len as u64;
+        if n < prefix_len {
+            return n as usize;
+        }
+        (prefix_len + (n - prefix_len) % self.period as u64) as usize
+    }
+}
+
+/* Floyd's tortoise and hare. Needs O(1) states in memory. */
+pub fn floyd<S, F>(start: &S, mut step: F) -> Cycle
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let mut tortoise = step(start);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        let hare_half = step(&hare);
+        hare = step(&hare_half);
+    }
+    /* Distance from start to the meeting point is a multiple of the period */
+    let mut prefix_len = 0;
+    tortoise = start.clone();
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        prefix_len += 1;
+    }
+    let mut period = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        period += 1;
+    }
+    Cycle {prefix_len, period}
+}
+
+/* Brent's algorithm. Needs O(1) states in memory and fewer steps than Floyd's. */
+pub fn brent<S, F>(start: &S, mut step: F) -> Cycle
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(start);
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        hare = step(&hare);
+        period += 1;
+    }
+    /* Hare runs `period` steps ahead, both meet at the start of the loop */
+    let mut prefix_len = 0;
+    tortoise = start.clone();
+    hare = start.clone();
+    for _ in 0..period {
+        hare = step(&hare);
+    }
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        prefix_len += 1;
+    }
+    Cycle {prefix_len, period}
+}
+
+/* Steps from start to the state at step n, skipping whole loops */
+pub fn state_at<S, F>(start: &S, mut step: F, cycle: &Cycle, n: u64) -> S
+where
+    S: Clone,
+    F: FnMut(&S) -> S,
+{
+    (0..cycle.reduce(n)).fold(start.clone(), |state, _| step(&state))
+}
+
+/* Keeps every state until the first repeat, so any step is a lookup afterwards.
+ * history[n] is the state after n steps. */
+#[derive(Clone,Debug)]
+pub struct HashedHistory<S> {
+    history: Vec<S>,
+    cycle: Cycle,
+}
+
+impl<S> HashedHistory<S>
+where
+    S: Clone + Eq + Hash,
+{
+    pub fn new<F>(start: &S, mut step: F) -> Self
+    where
+        F: FnMut(&S) -> S,
+    {
+        let mut seen = HashMap::<S,usize>::new();
+        let mut history = Vec::<S>::new();
+        let mut state = start.clone();
+        let prefix_len = loop {
+            if let Some(&idx) = seen.get(&state) {
+                break idx;
+            }
+            seen.insert(state.clone(), history.len());
+            let next = step(&state);
+            history.push(state);
+            state = next;
+        };
+        let period = history.len() - prefix_len;
+        HashedHistory {history, cycle: Cycle {prefix_len, period}}
+    }
+
+    pub fn cycle(&self) -> Cycle {
+        self.cycle
+    }
+
+    pub fn history(&self) -> &[S] {
+        &self.history
+    }
+
+    pub fn state_at(&self, n: u64) -> &S {
+        &self.history[self.cycle.reduce(n)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* 0 -> 1 -> 2 -> 3 -> 4 -> 5 -> 6 -> 3 */
+    fn rho_step(x: &u32) -> u32 {
+        if *x == 6 { 3 } else { x + 1 }
+    }
+
+    #[test]
+    fn cycle_detectors_agree() {
+        let expected = Cycle {prefix_len: 3, period: 4};
+        assert_eq!(floyd(&0, rho_step), expected);
+        assert_eq!(brent(&0, rho_step), expected);
+        assert_eq!(HashedHistory::new(&0, rho_step).cycle(), expected);
+    }
+
+    #[test]
+    fn cycle_pure_loop() {
+        let step = |x: &u8| (x + 1) % 5;
+        let expected = Cycle {prefix_len: 0, period: 5};
+        assert_eq!(floyd(&0, step), expected);
+        assert_eq!(brent(&0, step), expected);
+        assert_eq!(HashedHistory::new(&0, step).cycle(), expected);
+        let fixed_point = |x: &u8| *x;
+        assert_eq!(brent(&7, fixed_point), Cycle {prefix_len: 0, period: 1});
+        assert_eq!(floyd(&7, fixed_point), Cycle {prefix_len: 0, period: 1});
+    }
+
+    #[test]
+    fn cycle_state_at() {
+        let cycle = brent(&0, rho_step);
+        let history = HashedHistory::new(&0, rho_step);
+        assert_eq!(history.history(), &[0, 1, 2, 3, 4, 5, 6]);
+        for n in 0..50u64 {
+            let expected = (0..n).fold(0, |x, _| rho_step(&x));
+            assert_eq!(state_at(&0, rho_step, &cycle, n), expected);
+            assert_eq!(*history.state_at(n), expected);
+        }
+        assert_eq!(*history.state_at(1_000_000_000_000), (3 + (1_000_000_000_000u64 - 3) % 4) as u32);
+    }
+
+    #[test]
+    fn cycle_lcg() {
+        /* x -> x^2 + 1 mod 255 */
+        let step = |x: &u64| (x * x + 1) % 255;
+        let history = HashedHistory::new(&3, step);
+        assert_eq!(brent(&3, step), history.cycle());
+        assert_eq!(floyd(&3, step), history.cycle());
+    }
+}
@@ -1,3 +1,13 @@
+use nom::{
+    Parser,
+    IResult,
+    combinator::{all_consuming, map_res},
+    character::complete::{digit1, line_ending, space1},
+    bytes::complete::take_till1,
+    multi::separated_list1,
+    sequence::separated_pair,
+};
+
 #[aoc_generator(day1)]
 pub fn input_generator(input: &str) -> String {
     input.to_string()
@@ -33,61 +43,203 @@ pub fn solve_part1(input: &str) -> u32 {
         .sum()
 }
 
-const VALID_MATCHES: [&str; 19] = [
-    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "one", "two", "three", "four", "five", "six",
-    "seven", "eight", "nine",
-];
-
-fn word_to_digit(word: &str) -> u8 {
-    match word {
-        "0" => 0,
-        "1" | "one" => 1,
-        "2" | "two" => 2,
-        "3" | "three" => 3,
-        "4" | "four" => 4,
-        "5" | "five" => 5,
-        "6" | "six" => 6,
-        "7" | "seven" => 7,
-        "8" | "eight" => 8,
-        "9" | "nine" => 9,
-        _ => panic!("Invalid word: {}", word),
-    }
+pub const ENGLISH: &str = "0 0
+1 1
+2 2
+3 3
+4 4
+5 5
+6 6
+7 7
+8 8
+9 9
+one 1
+two 2
+three 3
+four 4
+five 5
+six 6
+seven 7
+eight 8
+nine 9";
+
+pub const GERMAN: &str = "0 0
+1 1
+2 2
+3 3
+4 4
+5 5
+6 6
+7 7
+8 8
+9 9
+null 0
+eins 1
+zwei 2
+drei 3
+vier 4
+fünf 5
+sechs 6
+sieben 7
+acht 8
+neun 9";
+
+pub const SERBIAN: &str = "0 0
+1 1
+2 2
+3 3
+4 4
+5 5
+6 6
+7 7
+8 8
+9 9
+nula 0
+jedan 1
+dva 2
+tri 3
+četiri 4
+pet 5
+šest 6
+sedam 7
+osam 8
+devet 9";
+
+/* Tokens that stand for a digit, compiled into an Aho-Corasick automaton over bytes so that
+ * a line is scanned once and overlapping tokens like "oneight" are all found */
+#[derive(Clone,Debug)]
+pub struct DigitLexicon {
+    tokens: Vec<(String, u8)>,
+    next: Vec<[u32; 256]>,//full transition table, state 0 is the root
+    out: Vec<Vec<usize>>,//tokens ending in every state, suffixes included
 }
 
-fn first_match<'a>(haystack: &str, words: &[&'a str]) -> &'a str {
-    for idx in 0..haystack.bytes().len() {
-        for &word in words {
-            if haystack.as_bytes()[idx..].starts_with(word.as_bytes()) {
-                return word;
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct DigitMatch {
+    pub start: usize,//byte offsets
+    pub end: usize,
+    pub digit: u8,
+}
+
+impl DigitLexicon {
+    pub fn new(tokens: &[(&str, u8)]) -> Self {
+        let tokens = tokens.iter()
+            .map(|(token, digit)| {
+                assert!(!token.is_empty(), "Empty token for {}", digit);
+                assert!(*digit < 10, "Not a digit: {}", digit);
+                (token.to_string(), *digit)
+            })
+            .collect::<Vec<(String, u8)>>();
+        /* Trie */
+        let mut next = vec![[0u32; 256]];
+        let mut out = vec![Vec::<usize>::new()];
+        for (idx, (token, _)) in tokens.iter().enumerate() {
+            let mut state = 0;
+            for byte in token.bytes() {
+                if next[state][byte as usize] == 0 {
+                    next[state][byte as usize] = next.len() as u32;
+                    next.push([0; 256]);
+                    out.push(Vec::new());
+                }
+                state = next[state][byte as usize] as usize;
             }
+            out[state].push(idx);
         }
+        /* Failure links in BFS order, missing edges point where the failure link would go */
+        let mut fail = vec![0usize; next.len()];
+        let mut queue = std::collections::VecDeque::<usize>::new();
+        queue.extend(next[0].iter().filter(|child| **child != 0).map(|child| *child as usize));
+        while let Some(state) = queue.pop_front() {
+            let inherited = out[fail[state]].clone();
+            out[state].extend(inherited);
+            let fallback = next[fail[state]];
+            for (child, fallback) in next[state].iter_mut().zip(fallback) {
+                if *child == 0 {
+                    *child = fallback;
+                } else {
+                    fail[*child as usize] = fallback as usize;
+                    queue.push_back(*child as usize);
+                }
+            }
+        }
+        DigitLexicon {tokens, next, out}
     }
-    panic!("No word found in: {}", haystack);
-}
 
-fn last_match<'a>(haystack: &str, words: &[&'a str]) -> &'a str {
-    for idx in (0..haystack.bytes().len()).rev() {
-        for &word in words {
-            if haystack.as_bytes()[idx..].starts_with(word.as_bytes()) {
-                return word;
+    /* One "token digit" pair per line */
+    pub fn from_table(table: &str) -> Self {
+        match all_consuming(separated_list1(line_ending, parse_table_entry))
+            .parse(table.trim_end())
+        {
+            Ok((_, val)) => DigitLexicon::new(&val),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn english() -> Self {
+        DigitLexicon::from_table(ENGLISH)
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = (&str, u8)> {
+        self.tokens.iter().map(|(token, digit)| (token.as_str(), *digit))
+    }
+
+    /* Every token in the line, ordered by end offset. Tokens ending at the same offset come
+     * longest first. */
+    pub fn find_all<'a>(&'a self, line: &'a str) -> impl Iterator<Item = DigitMatch> + 'a {
+        line.bytes()
+            .enumerate()
+            .scan(0usize, move |state, (pos, byte)| {
+                *state = self.next[*state][byte as usize] as usize;
+                Some((pos + 1, *state))
+            })
+            .flat_map(move |(end, state)| {
+                self.out[state].iter().map(move |idx| {
+                    let (token, digit) = &self.tokens[*idx];
+                    DigitMatch {start: end - token.len(), end, digit: *digit}
+                })
+            })
+    }
+
+    /* First digit (earliest start) and last digit (latest start) as a two digit number */
+    pub fn calibration_value(&self, line: &str) -> Option<u32> {
+        let mut first: Option<DigitMatch> = None;
+        let mut last: Option<DigitMatch> = None;
+        for found in self.find_all(line) {
+            if first.is_none_or(|first| found.start < first.start) {
+                first = Some(found);
+            }
+            if last.is_none_or(|last| found.start > last.start) {
+                last = Some(found);
             }
         }
+        Some(first?.digit as u32 * 10 + last?.digit as u32)
     }
-    panic!("No word found in: {}", haystack);
 }
 
-#[aoc(day1, part2)]
-pub fn solve_part2(input: &str) -> u32 {
+fn parse_table_entry(input: &str) -> IResult<&str, (&str, u8)> {
+    separated_pair(
+        take_till1(char::is_whitespace),
+        space1,
+        map_res(digit1, |digit: &str| digit.parse::<u8>()),
+    )
+    .parse(input)
+}
+
+pub fn calibrate(input: &str, lexicon: &DigitLexicon) -> u32 {
     input
         .lines()
         .map(|line| {
-            let first_digit = word_to_digit(first_match(line, &VALID_MATCHES));
-            let last_digit = word_to_digit(last_match(line, &VALID_MATCHES));
-            (first_digit * 10 + last_digit) as u32
+            lexicon.calibration_value(line)
+                .unwrap_or_else(|| panic!("No word found in: {}", line))
         })
     .sum()
 }
 
+#[aoc(day1, part2)]
+pub fn solve_part2(input: &str) -> u32 {
+    calibrate(input, &DigitLexicon::english())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,5 +266,43 @@ treb7uchet";
         let ans = solve_part1(&input);
         assert_eq!(ans, 142);
     }
-}
 
+    const TEST_INPUT2: &str = "two1nine
+eightwothree
+abcone2threexyz
+xtwone3four
+4nineeightseven2
+zoneight234
+7pqrstsixteen";
+
+    #[test]
+    fn test_solve_day1p2() {
+        let input = input_generator(TEST_INPUT2);
+        let ans = solve_part2(&input);
+        assert_eq!(ans, 281);
+    }
+
+    #[test]
+    fn test_day1_lexicon_overlaps() {
+        let english = DigitLexicon::english();
+        let digits = english.find_all("oneightwone")
+            .map(|found| (found.start, found.digit))
+            .collect::<Vec<(usize, u8)>>();
+        assert_eq!(digits, vec![(0, 1), (2, 8), (6, 2), (8, 1)]);
+        assert_eq!(english.calibration_value("oneight"), Some(18));
+        assert_eq!(english.calibration_value("abc"), None);
+        /* A token inside a longer one */
+        let custom = DigitLexicon::new(&[("abcd", 1), ("bc", 2), ("c", 3)]);
+        assert_eq!(custom.calibration_value("xabcdx"), Some(13));
+    }
+
+    #[test]
+    fn test_day1_lexicon_tables() {
+        let german = DigitLexicon::from_table(GERMAN);
+        assert_eq!(german.calibration_value("xfünfzweinullx"), Some(50));
+        let serbian = DigitLexicon::from_table(SERBIAN);
+        assert_eq!(serbian.calibration_value("četiriosamdevetri"), Some(43));
+        assert_eq!(DigitLexicon::from_table("I 1\nV 5\nX 0\n").calibration_value("XIV"), Some(5));
+        assert_eq!(DigitLexicon::english().tokens().count(), 19);
+    }
+}
@@ -1,5 +1,5 @@
-use std::collections::HashMap;
 use itertools::Itertools;
+use rayon::prelude::*;
 use nom::{
     Parser,
     IResult,
@@ -9,12 +9,155 @@ use nom::{
     multi::separated_list1,
 };
 
-#[allow(dead_code)]
+#[derive(Clone,Debug,PartialEq,Eq)]
 pub struct SpringConditionRecord {
     record: String,
     hints: Vec<u64>,
 }
 
+impl SpringConditionRecord {
+    pub fn new(record: &str, hints: &[u64]) -> Self {
+        SpringConditionRecord {record: record.to_string(), hints: hints.to_vec()}
+    }
+
+    pub fn record(&self) -> &str {
+        &self.record
+    }
+
+    pub fn hints(&self) -> &[u64] {
+        &self.hints
+    }
+
+    /* `factor` copies of the record joined by `separator`, hints repeated `factor` times */
+    pub fn unfold(&self, factor: usize, separator: char) -> SpringConditionRecord {
+        SpringConditionRecord {
+            record: (0..factor).map(|_| self.record.as_str()).join(&separator.to_string()),
+            hints: (0..factor).flat_map(|_| self.hints.iter().copied()).collect::<Vec<u64>>(),
+        }
+    }
+
+    pub fn count_arrangements(&self) -> u64 {
+        ArrangementTable::new(self.record.as_bytes(), &self.hints).count()
+    }
+
+    /* Every arrangement consistent with the hints in lexicographic order, '#' < '.' */
+    pub fn arrangements(&self) -> Arrangements<'_> {
+        let table = ArrangementTable::new(self.record.as_bytes(), &self.hints);
+        let total = table.count();
+        Arrangements {cond_rec: self, table, next: 0, total}
+    }
+
+    /* The k-th (from 0) arrangement in lexicographic order without listing the ones before */
+    pub fn kth_arrangement(&self, k: u64) -> Option<String> {
+        ArrangementTable::new(self.record.as_bytes(), &self.hints)
+            .kth(self.record.as_bytes(), &self.hints, k)
+    }
+}
+
+pub struct Arrangements<'a> {
+    cond_rec: &'a SpringConditionRecord,
+    table: ArrangementTable,
+    next: u64,
+    total: u64,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let out = self.table.kth(self.cond_rec.record.as_bytes(), &self.cond_rec.hints, self.next)?;
+        self.next += 1;
+        Some(out)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = usize::try_from(self.total - self.next).ok();
+        (left.unwrap_or(usize::MAX), left)
+    }
+}
+
+/* Bottom-up DP over (record position, group index).
+ * ways[i][j] counts the arrangements of record[i..] matching hints[j..], where record[i] is
+ * not directly after a damaged spring. Row len + 1 stands for "past the end after a group". */
+struct ArrangementTable {
+    num_hints: usize,
+    ways: Vec<u64>,
+}
+
+impl ArrangementTable {
+    fn new(record: &[u8], hints: &[u64]) -> Self {
+        let (len, num_hints) = (record.len(), hints.len());
+        /* Length of the run of possibly damaged springs starting at every position */
+        let mut run = vec![0usize; len + 1];
+        for i in (0..len).rev() {
+            run[i] = if record[i] == b'.' { 0 } else { run[i + 1] + 1 };
+        }
+        let mut ways = vec![0u64; (len + 2) * (num_hints + 1)];
+        ways[len * (num_hints + 1) + num_hints] = 1;
+        ways[(len + 1) * (num_hints + 1) + num_hints] = 1;
+        for i in (0..len).rev() {
+            for j in 0..=num_hints {
+                let mut out = 0;
+                if record[i] != b'#' {//operational
+                    out += ways[(i + 1) * (num_hints + 1) + j];
+                }
+                if record[i] != b'.' && j < num_hints {//group of damaged springs starts here
+                    let group = hints[j] as usize;
+                    if group <= run[i] && (i + group == len || record[i + group] != b'#') {
+                        out += ways[(i + group + 1) * (num_hints + 1) + j + 1];
+                    }
+                }
+                ways[i * (num_hints + 1) + j] = out;
+            }
+        }
+        ArrangementTable {num_hints, ways}
+    }
+
+    #[inline]
+    fn get(&self, pos: usize, group: usize) -> u64 {
+        self.ways[pos * (self.num_hints + 1) + group]
+    }
+
+    fn count(&self) -> u64 {
+        self.get(0, 0)
+    }
+
+    /* Walks down the table: starting a group puts a '#' first, so its arrangements come
+     * before the ones with an operational spring at the same position. */
+    fn kth(&self, record: &[u8], hints: &[u64], mut k: u64) -> Option<String> {
+        if k >= self.count() {
+            return None;
+        }
+        let len = record.len();
+        let mut out = String::with_capacity(len);
+        let (mut pos, mut group) = (0, 0);
+        while pos < len {
+            if record[pos] != b'.' && group < self.num_hints {
+                let size = hints[group] as usize;
+                if size <= len - pos
+                    && record[pos..pos + size].iter().all(|ch| *ch != b'.')
+                    && (pos + size == len || record[pos + size] != b'#')
+                {
+                    let ways = self.get(pos + size + 1, group + 1);
+                    if k < ways {
+                        out.extend((0..size).map(|_| '#'));
+                        if pos + size < len {
+                            out.push('.');
+                        }
+                        pos += size + 1;
+                        group += 1;
+                        continue;
+                    }
+                    k -= ways;
+                }
+            }
+            out.push('.');
+            pos += 1;
+        }
+        Some(out)
+    }
+}
+
 #[aoc_generator(day12)]
 pub fn input_generator(input: &str) -> Vec<SpringConditionRecord> {
     match all_consuming(separated_list1(tag("\n"), parse_spring_line))
@@ -112,112 +255,33 @@ fn count_possibilities(hints: &[u64], record: &str, num_springs: u64) -> u64 {
     out
 }
 
-#[allow(unused_assignments)]
-fn count_possibilities_memoized<'a>(hints: &'a [u64], record: &'a str, num_springs: u64, memo: &mut HashMap<(&'a [u64], &'a str,u64), u64>) -> u64 {
-    let mut out = 0;
-    if memo.contains_key(&(hints, record, num_springs)) {
-        out = *memo.get(&(hints, record, num_springs)).expect("Memo should've had the value");
-    } else if hints.len() == 0 {
-        if record.chars().any(|el| el == '#') {
-            return 0;
-        } else {//can only end in trailing SpringCondition::Damaged no matter what
-            return 1;
-        }
-    } else {
-        if record.len() == 0 {
-            return 0; //sequence doesn't fulfill all hints
-        }
-
-        let mut rec_iter = record.chars();
-        match rec_iter.next().unwrap() {
-            '#'=> {
-                if num_springs + 1 == *hints.first().unwrap() {
-                    /* check if consecutive SpringCondition::Operational exceeds hint length */
-                    if record.len() == 1 {//is last entry -> okay
-                        out = count_possibilities_memoized(&hints[1..], &record[1..], 0, memo);
-                    } else if rec_iter.next().unwrap() == '#' {
-                        out = 0;
-                    } else {
-                        out = count_possibilities_memoized(&hints[1..], &record[2..], 0, memo);
-                    }
-                } else {
-                    out = count_possibilities_memoized(&hints, &record[1..], num_springs + 1, memo);
-                }
-            },
-            '.' => {
-                if num_springs == 0 {
-                    out = count_possibilities_memoized(&hints, &record[1..], 0, memo);
-                } else {//failed to match a hint
-                    out = 0;
-                }
-            },
-            '?' => {
-                /* Count if Operational */
-                out = if num_springs + 1 == *hints.first().unwrap() {
-                    /* check if consecutive SpringCondition::Operational exceeds hint length */
-                    if record.len() == 1 {
-                        count_possibilities_memoized(&hints[1..], &record[1..], 0, memo)
-                    } else if rec_iter.next().unwrap() == '#' {
-                        0
-                    } else {
-                        count_possibilities_memoized(&hints[1..], &record[2..], 0, memo)
-                    }
-                } else {
-                    count_possibilities_memoized(&hints, &record[1..], num_springs + 1, memo)
-                };
-                /* Count if Damaged */
-                out += if num_springs == 0 {
-                    count_possibilities_memoized(&hints, &record[1..], 0, memo)
-                } else {
-                    0
-                };
-            },
-            _ => unreachable!("Expected one of : \'#\', \'.\', \'?\'"),
-        }
-    }
-    memo.insert((hints, record,num_springs), out);
-    out
-}
-
 #[aoc(day12, part1)]
 pub fn solve_part1(input: &[SpringConditionRecord]) -> u64 {
-    input.iter()
-        .map(|cond_rec| {
-            count_possibilities(&cond_rec.hints, &cond_rec.record, 0)
-        })
-    .sum()
+    input.par_iter()
+        .map(SpringConditionRecord::count_arrangements)
+        .sum()
 }
 
 #[allow(dead_code)]
-fn solve_part1_memoized(input: &[SpringConditionRecord]) -> u64 {
-    let mut memo = HashMap::<(&[u64],&str,u64),u64>::new();
+fn solve_part1_recursive(input: &[SpringConditionRecord]) -> u64 {
     input.iter()
         .map(|cond_rec| {
-            count_possibilities_memoized(&cond_rec.hints, &cond_rec.record, 0, &mut memo)
+            count_possibilities(&cond_rec.hints, &cond_rec.record, 0)
         })
     .sum()
 }
 
 #[aoc(day12, part2)]
 pub fn solve_part2(input: &[SpringConditionRecord]) -> u64 {
-    let unfolded_input = input.iter()
-        .map(|cond_rec| SpringConditionRecord{
-            record: (0..5).into_iter().map(|_| cond_rec.record.clone()).join("?"),
-            hints: (0..5).flat_map(|_|  cond_rec.hints.clone()).collect::<Vec<_>>(),
-        })
-    .collect::<Vec<SpringConditionRecord>>();
-    /* Solve problem */
-    let mut memo = HashMap::<(&[u64],&str,u64),u64>::new();
-    unfolded_input.iter()
-        .map(|cond_rec| {
-            count_possibilities_memoized(&cond_rec.hints, &cond_rec.record, 0, &mut memo)
-        })
-    .sum()
+    input.par_iter()
+        .map(|cond_rec| cond_rec.unfold(5, '?').count_arrangements())
+        .sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TestRng;
     const TEST_INPUT: &str =
 "???.### 1,1,3
 .??..??...?##. 1,1,3
@@ -309,9 +373,9 @@ mod tests {
 
     #[test]
     #[ignore]
-    fn day12_solve_p1_memoized() {
+    fn day12_solve_p1_recursive() {
         let input1 = input_generator(TEST_INPUT);
-        let ans = solve_part1(&input1);
+        let ans = solve_part1_recursive(&input1);
         assert_eq!(ans, 21);
     }
 
@@ -321,4 +385,87 @@ mod tests {
         let ans = solve_part2(&input2);
         assert_eq!(ans, 525152);
     }
+
+    #[test]
+    fn day12_unfold() {
+        let rec = SpringConditionRecord::new(".#", &[1]);
+        let unfolded = rec.unfold(5, '?');
+        assert_eq!(unfolded.record(), ".#?.#?.#?.#?.#");
+        assert_eq!(unfolded.hints(), &[1, 1, 1, 1, 1]);
+        assert_eq!(rec.unfold(1, '?'), rec);
+        assert_eq!(SpringConditionRecord::new("???.###", &[1,1,3]).unfold(5, '?').count_arrangements(), 1);
+        assert_eq!(SpringConditionRecord::new("?###????????", &[3,2,1]).unfold(5, '?').count_arrangements(), 506250);
+    }
+
+    /* Random small records, the DP has to agree with the recursive count */
+    #[test]
+    fn day12_dp_matches_recursive() {
+        let mut rng = TestRng::new(0x12);
+        for _ in 0..2000 {
+            let len = 1 + rng.below(12) as usize;
+            let record = (0..len)
+                .map(|_| ['.', '#', '?', '?'][rng.below(4) as usize])
+                .collect::<String>();
+            let num_hints = 1 + rng.below(4) as usize;
+            let hints = (0..num_hints)
+                .map(|_| 1 + rng.below(4))
+                .collect::<Vec<u64>>();
+            let rec = SpringConditionRecord::new(&record, &hints);
+            assert_eq!(rec.count_arrangements(), count_possibilities(&hints, &record, 0),
+                "{} {:?}", record, hints);
+        }
+    }
+
+    #[test]
+    fn day12_arrangements() {
+        let rec = SpringConditionRecord::new("?###????????", &[3,2,1]);
+        let all = rec.arrangements().collect::<Vec<String>>();
+        assert_eq!(all.len(), 10);
+        assert_eq!(all[0], ".###.##.#...");
+        assert_eq!(all[9], ".###....##.#");
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(rec.kth_arrangement(3), Some(all[3].clone()));
+        assert_eq!(rec.kth_arrangement(10), None);
+        assert_eq!(SpringConditionRecord::new("#.#", &[2]).arrangements().next(), None);
+    }
+
+    /* Random small records against substituting every '?' by hand */
+    #[test]
+    fn day12_arrangements_brute_force() {
+        let matches_hints = |arrangement: &str, hints: &[u64]| {
+            arrangement.split('.')
+                .filter(|group| !group.is_empty())
+                .map(|group| group.len() as u64)
+                .eq(hints.iter().copied())
+        };
+        let mut rng = TestRng::new(0x42);
+        for _ in 0..500 {
+            let len = 1 + rng.below(10) as usize;
+            let record = (0..len)
+                .map(|_| ['.', '#', '?', '?'][rng.below(4) as usize])
+                .collect::<String>();
+            let hints = (0..1 + rng.below(3))
+                .map(|_| 1 + rng.below(3))
+                .collect::<Vec<u64>>();
+            let unknowns = record.chars().filter(|ch| *ch == '?').count();
+            let mut expected = (0..1u32 << unknowns)
+                .map(|mask| {
+                    let mut bit = 0;
+                    record.chars()
+                        .map(|ch| match ch {
+                            '?' => {
+                                bit += 1;
+                                if mask >> (bit - 1) & 1 == 1 { '#' } else { '.' }
+                            },
+                            _ => ch,
+                        })
+                        .collect::<String>()
+                })
+                .filter(|arrangement| matches_hints(arrangement, &hints))
+                .collect::<Vec<String>>();
+            expected.sort();
+            let rec = SpringConditionRecord::new(&record, &hints);
+            assert_eq!(rec.arrangements().collect::<Vec<String>>(), expected, "{} {:?}", record, hints);
+        }
+    }
 }
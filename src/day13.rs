@@ -1,136 +1,212 @@
+use std::ops::BitXor;
+
+/* Row or column of a pattern packed into an integer, first cell in the highest used bit */
+pub trait LineBits: Copy + Eq + BitXor<Output = Self> {
+    const BITS: u32;
+    fn zero() -> Self;
+    fn push_bit(self, bit: bool) -> Self;
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+    fn clear_lowest_bit(self) -> Self;
+}
+
+macro_rules! impl_line_bits {
+    ($($t:ty),*) => {$(
+        impl LineBits for $t {
+            const BITS: u32 = <$t>::BITS;
+            fn zero() -> Self { 0 }
+            fn push_bit(self, bit: bool) -> Self { (self << 1) | bit as $t }
+            fn count_ones(self) -> u32 { <$t>::count_ones(self) }
+            fn trailing_zeros(self) -> u32 { <$t>::trailing_zeros(self) }
+            fn clear_lowest_bit(self) -> Self { self & self.wrapping_sub(1) }
+        }
+    )*};
+}
+
+impl_line_bits!(u32, u64, u128);
+
 #[derive(Debug)]
-pub struct Landscape {
-    cols: Vec<u32>,
-    rows: Vec<u32>,
+pub struct Landscape<B = u128> {
+    cols: Vec<B>,
+    rows: Vec<B>,
 }
 
 #[aoc_generator(day13)]
 pub fn generate(input: &str) -> Vec<Landscape> {
     input
         .split("\n\n")
-        .map(|landscape| {
-            let col_len = landscape.find('\n').unwrap();
-            let mut cols = vec![0; col_len];
-            let mut rows = vec![0; 1];
-            let mut col_idx = 0;
-            let mut row_idx = 0;
-            for ch in landscape.chars() {
-                if ch == '\n' {
-                    rows.push(0);
-                    row_idx += 1;
-                    col_idx = 0;
-                    continue;
-                }
-                let val = (ch == '#') as u32;
-                unsafe {
-                    let c = cols.get_unchecked_mut(col_idx);
-                    *c <<= 1;
-                    *c += val;
-                    let r = rows.get_unchecked_mut(row_idx);
-                    *r <<= 1;
-                    *r += val;
-                }
-
-                col_idx += 1;
-            }
-            Landscape { rows, cols }
-        })
+        .map(parse_landscape)
         .collect()
 }
 
-fn try_reflect(images: &[u32]) -> usize {
-    let mut reflected = false;
-    let mut idx = 1;
-    while !reflected && idx < images.len() {
-        reflected = true;
-        let mut lo = idx;
-        let mut hi = idx - 1;
-        while lo > 0 && hi < images.len() - 1 {
-            lo -= 1;
-            hi += 1;
-            if images[lo] != images[hi] {
-                reflected = false;
-                break;
-            }
-        }
-        if reflected {
-            break;
+/* Patterns can be at most B::BITS wide and high. The solvers use u128 lines. */
+pub fn parse_landscape<B: LineBits>(landscape: &str) -> Landscape<B> {
+    let col_len = landscape.find('\n').unwrap_or(landscape.len());
+    let row_len = landscape.lines().count();
+    assert!(col_len <= B::BITS as usize && row_len <= B::BITS as usize,
+        "Pattern is {}x{}, but lines only hold {} bits", row_len, col_len, B::BITS);
+    let mut cols = vec![B::zero(); col_len];
+    let mut rows = vec![B::zero(); row_len];
+    for (row_idx, line) in landscape.lines().enumerate() {
+        for (col_idx, ch) in line.chars().enumerate() {
+            let val = ch == '#';
+            cols[col_idx] = cols[col_idx].push_bit(val);
+            rows[row_idx] = rows[row_idx].push_bit(val);
         }
-        idx += 1;
     }
+    Landscape { rows, cols }
+}
 
-    if reflected {
-        idx
-    } else {
-        0
-    }
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Axis {
+    Vertical(usize),  // number of columns left of the mirror
+    Horizontal(usize),// number of rows above the mirror
 }
 
-fn one_bit_different(a: u32, b: u32) -> bool {
-    let val = a ^ b;
-    val & (val - 1) == 0
+/* Mirror axis and the pairs of mirrored (row, col) cells that differ */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Reflection {
+    pub axis: Axis,
+    pub smudges: Vec<((usize,usize),(usize,usize))>,
 }
 
-fn try_reflect_smudged(images: &[u32]) -> usize {
-    let mut reflected = false;
-    let mut smudged = false;
-    let mut idx = 1;
-    while idx < images.len() {
-        reflected = true;
-        smudged = false;
-        let mut lo = idx;
-        let mut hi = idx - 1;
-        while lo > 0 && hi < images.len() - 1 {
-            lo -= 1;
-            hi += 1;
-            if images[lo] == images[hi] {
-                continue;
-            } else if !smudged && one_bit_different(images[lo], images[hi]) {
-                smudged = true;
-            } else {
-                reflected = false;
+/* Mirrored pair of lines and the position in them of a differing cell */
+type LineDiff = (usize,usize,usize);
+
+/* Every axis between lines where exactly `smudges` mirrored cells differ.
+ * Returns the number of lines before each axis with its sorted differences. */
+fn try_reflect<B: LineBits>(images: &[B], line_len: usize, smudges: u32) -> Vec<(usize, Vec<LineDiff>)> {
+    let mut out = Vec::new();
+    for idx in 1..images.len() {
+        let mut diff_count = 0;
+        let mut diffs = Vec::new();
+        for (lo, hi) in (0..idx).rev().zip(idx..images.len()) {
+            let mut diff = images[lo] ^ images[hi];
+            diff_count += diff.count_ones();
+            if diff_count > smudges {
                 break;
             }
+            while diff != B::zero() {
+                diffs.push((lo, hi, line_len - 1 - diff.trailing_zeros() as usize));
+                diff = diff.clear_lowest_bit();
+            }
         }
-        if reflected && smudged {
-            break;
+        if diff_count == smudges {
+            diffs.sort_unstable();
+            out.push((idx, diffs));
         }
-        idx += 1;
-    }
-
-    if reflected && smudged {
-        idx
-    } else {
-        0
     }
+    out
 }
 
-fn reflection_score(landscape: &Landscape) -> usize {
-    let vert = try_reflect(&landscape.cols);
-    if vert > 0 {
-        vert
-    } else {
-        let horiz = try_reflect(&landscape.rows);
-        horiz * 100
-    }
+/* All vertical and horizontal mirror axes with exactly `smudges` differing cells */
+pub fn find_reflections<B: LineBits>(landscape: &Landscape<B>, smudges: u32) -> Vec<Reflection> {
+    let (height, width) = (landscape.rows.len(), landscape.cols.len());
+    let vertical = try_reflect(&landscape.cols, height, smudges).into_iter()
+        .map(|(idx, diffs)| Reflection {
+            axis: Axis::Vertical(idx),
+            smudges: diffs.into_iter().map(|(lo, hi, row)| ((row, lo), (row, hi))).collect(),
+        });
+    let horizontal = try_reflect(&landscape.rows, width, smudges).into_iter()
+        .map(|(idx, diffs)| Reflection {
+            axis: Axis::Horizontal(idx),
+            smudges: diffs.into_iter().map(|(lo, hi, col)| ((lo, col), (hi, col))).collect(),
+        });
+    vertical.chain(horizontal).collect()
 }
 
-fn reflection_score_smudged(landscape: &Landscape) -> usize {
-    let vert = try_reflect_smudged(&landscape.cols);
-    if vert > 0 {
-        vert
-    } else {
-        let horiz = try_reflect_smudged(&landscape.rows);
-        horiz * 100
+fn reflection_score<B: LineBits>(landscape: &Landscape<B>, smudges: u32) -> usize {
+    match find_reflections(landscape, smudges).first().map(|r| r.axis) {
+        Some(Axis::Vertical(idx)) => idx,
+        Some(Axis::Horizontal(idx)) => idx * 100,
+        None => 0,
     }
 }
 
 #[aoc(day13, part1)]
 pub fn reflections(input: &[Landscape]) -> usize {
-    input.iter().map(reflection_score).sum()
+    input.iter().map(|landscape| reflection_score(landscape, 0)).sum()
 }
 
 #[aoc(day13, part2)]
 pub fn reflections_smudged(input: &[Landscape]) -> usize {
-    input.iter().map(reflection_score_smudged).sum()
+    input.iter().map(|landscape| reflection_score(landscape, 1)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const TEST_INPUT: &str =
+"#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.
+
+#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#";
+
+    #[test]
+    fn day13_solve_p1() {
+        let input = generate(TEST_INPUT);
+        assert_eq!(reflections(&input), 405);
+    }
+
+    #[test]
+    fn day13_solve_p2() {
+        let input = generate(TEST_INPUT);
+        assert_eq!(reflections_smudged(&input), 400);
+    }
+
+    #[test]
+    fn day13_find_reflections() {
+        let input = generate(TEST_INPUT);
+        assert_eq!(find_reflections(&input[0], 0), vec![Reflection {axis: Axis::Vertical(5), smudges: vec![]}]);
+        assert_eq!(find_reflections(&input[1], 0), vec![Reflection {axis: Axis::Horizontal(4), smudges: vec![]}]);
+        /* The puzzle's smudges: (0,0) in the first pattern, (1,4) in the second */
+        assert_eq!(find_reflections(&input[0], 1), vec![Reflection {axis: Axis::Horizontal(3), smudges: vec![((0,0),(5,0))]}]);
+        assert_eq!(find_reflections(&input[1], 1), vec![Reflection {axis: Axis::Horizontal(1), smudges: vec![((0,4),(1,4))]}]);
+    }
+
+    #[test]
+    fn day13_multiple_axes() {
+        let landscape = parse_landscape::<u32>("#..#\n#..#\n#..#\n#..#");
+        let axes = find_reflections(&landscape, 0).into_iter()
+            .map(|r| r.axis)
+            .collect::<Vec<Axis>>();
+        assert_eq!(axes, vec![Axis::Vertical(2), Axis::Horizontal(1), Axis::Horizontal(2), Axis::Horizontal(3)]);
+        let four_smudges = find_reflections(&landscape, 4);
+        assert_eq!(four_smudges.iter().map(|r| r.axis).collect::<Vec<Axis>>(), vec![Axis::Vertical(1), Axis::Vertical(3)]);
+        assert_eq!(four_smudges[0].smudges, vec![((0,0),(0,1)), ((1,0),(1,1)), ((2,0),(2,1)), ((3,0),(3,1))]);
+    }
+
+    #[test]
+    fn day13_wide_pattern() {
+        let half = "#.##..##.#..#.###.#.";//20 columns
+        let mirrored = half.chars().rev().collect::<String>();
+        let line = format!("{}{}", half, mirrored);
+        let pattern = format!("{}\n.{}\n{}", line, &line[1..], line);
+        let landscape = parse_landscape::<u64>(&pattern);
+        assert_eq!(landscape.cols.len(), 40);
+        assert!(find_reflections(&landscape, 0).iter().all(|r| r.axis != Axis::Vertical(20)));
+        let smudged = find_reflections(&landscape, 1);
+        assert!(smudged.contains(&Reflection {axis: Axis::Vertical(20), smudges: vec![((1,0),(1,39))]}));
+        /* The aoc entry points take wide patterns too */
+        let input = generate(&pattern);
+        assert_eq!(input[0].cols.len(), 40);
+        assert_eq!(reflections_smudged(&input), reflection_score(&landscape, 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn day13_too_wide_for_u32() {
+        parse_landscape::<u32>(&"#.".repeat(20));
+    }
 }
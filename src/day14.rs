@@ -1,6 +1,9 @@
 use ndarray::*;
-use std::collections::HashMap;
 use std::fmt::{self, Display};
+use std::hash::Hasher;
+use fnv::FnvHasher;
+use crate::cycle::{Cycle, HashedHistory};
+use num::{BigUint, ToPrimitive};
 use nom::{
     Parser,
     IResult,
@@ -192,38 +195,320 @@ fn one_cycle_for_p2(input: &mut Array2<RockType>, out: &mut Array2<RockType>) {
     *input = out.clone();
 }
 
-fn find_cycle_len(input: &mut Array2<RockType>) -> u64 {
-    let mut results = HashMap::<Array2<RockType>,u64>::new();
-    let mut counter = 0;
+fn north_load_after_spin_cycles(input: &Array2<RockType>, cycles: u64) -> u64 {
     let mut out = input.clone();
-    loop {
-        if results.contains_key(&out) {
-            break;
-        } else if counter > 1000 {
-            panic!("Taking too long to find cycle length");
-        }
-        results.insert(out.clone(), counter);
-        one_cycle_for_p2(input, &mut out);
-        counter += 1;
-    }
-    let offset = results.get(&out).unwrap().clone();
-    let cycle_len = counter - offset;
-    let rem = (1_000_000_000u64 - offset) % cycle_len;
-    results.iter()
-        .filter(|(_,val)| **val == rem + offset)
-        .map(|(key,_)| calc_north_side_load(key))
-        .next().unwrap()
+    let history = HashedHistory::new(input, |platform| {
+        let mut platform = platform.clone();
+        one_cycle_for_p2(&mut platform, &mut out);
+        platform
+    });
+    calc_north_side_load(history.state_at(cycles))
 }
 
 #[aoc(day14, part2)]
 pub fn solve_part2(input: &Array2<RockType>) -> u64 {
-    let mut out = input.clone();
-    find_cycle_len(&mut out)
+    north_load_after_spin_cycles(input, 1_000_000_000)
+}
+
+/* Bit-packed platform: bit j of a row is column j. Holds platforms up to 128 columns wide. */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct BitPlatform {
+    round: Vec<u128>,
+    cube: Vec<u128>,
+    segments: Vec<Vec<(u32,u32)>>,//per row: (first, last) column between cube rocks
+    cols: u32,
+}
+
+#[inline]
+fn low_bits(n: u32) -> u128 {
+    if n >= 128 { u128::MAX } else { (1u128 << n) - 1 }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct PlatformTooWide {
+    pub cols: usize,
+}
+
+impl Display for PlatformTooWide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Platform has {} columns, BitPlatform holds at most 128", self.cols)
+    }
+}
+
+impl BitPlatform {
+    pub fn new(input: &Array2<RockType>) -> Result<Self, PlatformTooWide> {
+        let (row_max, col_max) = input.dim();
+        if col_max > 128 {
+            return Err(PlatformTooWide {cols: col_max});
+        }
+        let mut round = vec![0u128; row_max];
+        let mut cube = vec![0u128; row_max];
+        for ((i,j), el) in input.indexed_iter() {
+            match el {
+                RockType::Rollable => round[i] |= 1 << j,
+                RockType::Fixed => cube[i] |= 1 << j,
+                RockType::Empty => {},
+            }
+        }
+        let cols = col_max as u32;
+        let segments = cube.iter()
+            .map(|row| {
+                let mut segs = Vec::<(u32,u32)>::new();
+                let mut first = 0;
+                for j in 0..=cols {
+                    if j == cols || row & (1 << j) != 0 {
+                        if first < j {
+                            segs.push((first, j - 1));
+                        }
+                        first = j + 1;
+                    }
+                }
+                segs
+            })
+            .collect();
+        Ok(BitPlatform {round, cube, segments, cols})
+    }
+
+    pub fn to_array(&self) -> Array2<RockType> {
+        Array2::from_shape_fn((self.round.len(), self.cols as usize), |(i,j)| {
+            if self.round[i] & (1 << j) != 0 {
+                RockType::Rollable
+            } else if self.cube[i] & (1 << j) != 0 {
+                RockType::Fixed
+            } else {
+                RockType::Empty
+            }
+        })
+    }
+
+    /* Rocks of a row move up through free cells of all columns at once. Rows above are
+     * already tilted, so each row only needs one pass. */
+    pub fn tilt_north(&mut self) {
+        for row_idx in 1..self.round.len() {
+            let mut moving = self.round[row_idx];
+            let mut k = row_idx;
+            while k > 0 && moving != 0 {
+                let free = !(self.round[k - 1] | self.cube[k - 1]);
+                let step = moving & free;
+                if step == 0 {
+                    break;
+                }
+                self.round[k] &= !step;
+                self.round[k - 1] |= step;
+                moving = step;
+                k -= 1;
+            }
+        }
+    }
+
+    pub fn tilt_south(&mut self) {
+        let row_max = self.round.len();
+        for row_idx in (0..row_max.saturating_sub(1)).rev() {
+            let mut moving = self.round[row_idx];
+            let mut k = row_idx;
+            while k + 1 < row_max && moving != 0 {
+                let free = !(self.round[k + 1] | self.cube[k + 1]);
+                let step = moving & free;
+                if step == 0 {
+                    break;
+                }
+                self.round[k] &= !step;
+                self.round[k + 1] |= step;
+                moving = step;
+                k += 1;
+            }
+        }
+    }
+
+    /* Every segment between cube rocks keeps its number of rocks, packed to the low end */
+    pub fn tilt_west(&mut self) {
+        for (row, segs) in self.round.iter_mut().zip(self.segments.iter()) {
+            let mut out = 0u128;
+            for (first, last) in segs.iter() {
+                let mask = low_bits(last - first + 1) << first;
+                out |= low_bits((*row & mask).count_ones()) << first;
+            }
+            *row = out;
+        }
+    }
+
+    pub fn tilt_east(&mut self) {
+        for (row, segs) in self.round.iter_mut().zip(self.segments.iter()) {
+            let mut out = 0u128;
+            for (first, last) in segs.iter() {
+                let mask = low_bits(last - first + 1) << first;
+                let n = (*row & mask).count_ones();
+                if n > 0 {//an empty segment ending at column 127 would shift by 128
+                    out |= low_bits(n) << (last + 1 - n);
+                }
+            }
+            *row = out;
+        }
+    }
+
+    pub fn spin_cycle(&mut self) {
+        self.tilt_north();
+        self.tilt_west();
+        self.tilt_south();
+        self.tilt_east();
+    }
+
+    pub fn north_load(&self) -> u64 {
+        let row_max = self.round.len();
+        self.round.iter()
+            .enumerate()
+            .map(|(i,row)| (row_max - i) as u64 * row.count_ones() as u64)
+            .sum()
+    }
+
+    pub fn tilt(&mut self, direction: Tilt) {
+        match direction {
+            Tilt::North => self.tilt_north(),
+            Tilt::West => self.tilt_west(),
+            Tilt::South => self.tilt_south(),
+            Tilt::East => self.tilt_east(),
+        }
+    }
+
+    /* Load on a side: every round rock weighs its distance to the opposite side plus one */
+    pub fn load(&self, side: Tilt) -> u64 {
+        let row_max = self.round.len() as u64;
+        let cols = self.cols as u64;
+        self.round.iter()
+            .enumerate()
+            .map(|(i,row)| match side {
+                Tilt::North => (row_max - i as u64) * row.count_ones() as u64,
+                Tilt::South => (i as u64 + 1) * row.count_ones() as u64,
+                Tilt::West => (0..self.cols)
+                    .filter(|j| row & (1 << j) != 0)
+                    .map(|j| cols - j as u64)
+                    .sum(),
+                Tilt::East => (0..self.cols)
+                    .filter(|j| row & (1 << j) != 0)
+                    .map(|j| j as u64 + 1)
+                    .sum(),
+            })
+            .sum()
+    }
+
+    /* Cheap 64-bit fingerprint of the round rocks for cycle detection */
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        for row in self.round.iter() {
+            hasher.write_u128(*row);
+        }
+        hasher.finish()
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum Tilt {
+    North,
+    West,
+    South,
+    East,
+}
+
+pub const SPIN_CYCLE: [Tilt; 4] = [Tilt::North, Tilt::West, Tilt::South, Tilt::East];
+
+/* States of repeatedly applying one cycle of tilts until a state repeats.
+ * Cube rocks never move, so only the round rocks of every state are kept. */
+#[derive(Clone,Debug)]
+pub struct SpinHistory {
+    start: BitPlatform,
+    rounds: HashedHistory<Vec<u128>>,
+}
+
+impl SpinHistory {
+    pub fn new(start: &BitPlatform, cycle: &[Tilt]) -> Self {
+        let mut platform = start.clone();
+        let rounds = HashedHistory::new(&start.round, |round| {
+            platform.round.clone_from(round);
+            cycle.iter().for_each(|direction| platform.tilt(*direction));
+            platform.round.clone()
+        });
+        SpinHistory {start: start.clone(), rounds}
+    }
+
+    pub fn cycle(&self) -> Cycle {
+        self.rounds.cycle()
+    }
+
+    pub fn prefix_len(&self) -> usize {
+        self.rounds.cycle().prefix_len
+    }
+
+    pub fn period(&self) -> usize {
+        self.rounds.cycle().period
+    }
+
+    /* Platform after any number of cycles, reduced into the loop */
+    pub fn state_after(&self, repetitions: &BigUint) -> BitPlatform {
+        let Cycle {prefix_len, period} = self.cycle();
+        let round = match repetitions.to_u64() {
+            Some(n) => self.rounds.state_at(n),
+            None => {
+                let offset = (repetitions - prefix_len) % period;
+                &self.rounds.history()[prefix_len + offset.to_usize().unwrap()]
+            },
+        };
+        BitPlatform {
+            round: round.clone(),
+            cube: self.start.cube.clone(),
+            segments: self.start.segments.clone(),
+            cols: self.start.cols,
+        }
+    }
+}
+
+#[derive(Clone,Debug)]
+pub struct SpinOutcome {
+    pub platform: BitPlatform,
+    pub north_load: u64,
+}
+
+impl SpinOutcome {
+    pub fn load(&self, side: Tilt) -> u64 {
+        self.platform.load(side)
+    }
+}
+
+/* Applies the tilt sequence `cycle` `repetitions` times */
+pub fn spin(start: &BitPlatform, cycle: &[Tilt], repetitions: &BigUint) -> SpinOutcome {
+    let platform = SpinHistory::new(start, cycle)
+        .state_after(repetitions);
+    let north_load = platform.north_load();
+    SpinOutcome {platform, north_load}
+}
+
+pub fn input_generator_bits(input: &str) -> Result<BitPlatform, PlatformTooWide> {
+    BitPlatform::new(&input_generator(input))
+}
+
+/* Platforms wider than 128 columns fall back to the Array2 solvers */
+#[aoc(day14, part1, Bits)]
+pub fn solve_part1_bits(input: &Array2<RockType>) -> u64 {
+    match BitPlatform::new(input) {
+        Ok(mut platform) => {
+            platform.tilt_north();
+            platform.north_load()
+        },
+        Err(_) => solve_part1(input),
+    }
+}
+
+#[aoc(day14, part2, Bits)]
+pub fn solve_part2_bits(input: &Array2<RockType>) -> u64 {
+    match BitPlatform::new(input) {
+        Ok(platform) => spin(&platform, &SPIN_CYCLE, &BigUint::from(1_000_000_000u64)).north_load,
+        Err(_) => solve_part2(input),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TestRng;
     const TEST_INPUT: &str =
 "O....#....
 O.OO#....#
@@ -290,10 +575,10 @@ O.#..O.#.#
     }
 
     #[test]
-    fn day14_find_cycle_len() {
-        let mut input = input_generator(TEST_INPUT);
-        let ans = find_cycle_len(&mut input);
-        assert_eq!(ans, 64);
+    fn day14_north_load_after_spin_cycles() {
+        let input = input_generator(TEST_INPUT);
+        assert_eq!(north_load_after_spin_cycles(&input, 1_000_000_000), 64);
+        assert_eq!(north_load_after_spin_cycles(&input, 0), 104);
     }
 
     #[test]
@@ -302,4 +587,127 @@ O.#..O.#.#
         let ans = solve_part1(&input);
         assert_eq!(ans, 136);
     }
+
+    fn generate_platform(rows: usize, cols: usize, seed: u64) -> Array2<RockType> {
+        let mut rng = TestRng::new(seed);
+        Array2::from_shape_fn((rows, cols), |_| {
+            match rng.below(10) {
+                0 | 1 => RockType::Fixed,
+                2..=4 => RockType::Rollable,
+                _ => RockType::Empty,
+            }
+        })
+    }
+
+    #[test]
+    fn day14_bit_platform_tilts() {
+        for input in [input_generator(TEST_INPUT), generate_platform(37, 128, 14), generate_platform(100, 100, 3)] {
+            let mut bits = BitPlatform::new(&input).unwrap();
+            assert_eq!(bits.to_array(), input);
+            let mut arr = input.clone();
+            let mut out = input.clone();
+            for direction in SPIN_CYCLE.iter().cycle().take(12) {
+                bits.tilt(*direction);
+                match direction {
+                    Tilt::North => tilt_north(&arr, &mut out),
+                    Tilt::West => tilt_west(&arr, &mut out),
+                    Tilt::South => tilt_south(&arr, &mut out),
+                    Tilt::East => tilt_east(&arr, &mut out),
+                }
+                arr = out.clone();
+                assert_eq!(bits.to_array(), arr);
+                assert_eq!(bits.north_load(), calc_north_side_load(&arr));
+            }
+        }
+    }
+
+    #[test]
+    fn day14_bit_platform_fingerprint() {
+        let mut bits = input_generator_bits(TEST_INPUT).unwrap();
+        let start = bits.fingerprint();
+        bits.tilt_north();
+        assert_ne!(bits.fingerprint(), start);
+        assert_eq!(bits.fingerprint(), BitPlatform::new(&bits.to_array()).unwrap().fingerprint());
+    }
+
+    #[test]
+    fn day14_solve_bits() {
+        let input = input_generator(TEST_INPUT);
+        assert_eq!(solve_part1_bits(&input), 136);
+        assert_eq!(solve_part2_bits(&input), 64);
+        /* Too wide for the bits */
+        let wide = generate_platform(20, 130, 5);
+        assert_eq!(BitPlatform::new(&wide), Err(PlatformTooWide {cols: 130}));
+        assert_eq!(solve_part1_bits(&wide), solve_part1(&wide));
+        assert_eq!(solve_part2_bits(&wide), solve_part2(&wide));
+    }
+
+    /* Run with: cargo test --release day14_bench_bits -- --ignored --nocapture */
+    #[test]
+    #[ignore]
+    fn day14_bench_bits() {
+        let input = generate_platform(100, 100, 2023);
+        let start = std::time::Instant::now();
+        let ans = solve_part2(&input);
+        let t_array = start.elapsed();
+        let start = std::time::Instant::now();
+        let ans_bits = solve_part2_bits(&input);
+        let t_bits = start.elapsed();
+        println!("Array2: {:?}, bit-packed: {:?}", t_array, t_bits);
+        assert_eq!(ans, ans_bits);
+    }
+
+    #[test]
+    fn day14_spin_history() {
+        let input = input_generator_bits(TEST_INPUT).unwrap();
+        let history = SpinHistory::new(&input, &SPIN_CYCLE);
+        assert_eq!(history.prefix_len(), 3);
+        assert_eq!(history.period(), 7);
+        assert_eq!(history.state_after(&BigUint::from(0u32)), input);
+        let mut after_one = input.clone();
+        after_one.spin_cycle();
+        assert_eq!(history.state_after(&BigUint::from(1u32)), after_one);
+        assert_eq!(history.state_after(&BigUint::from(10u32)), history.state_after(&BigUint::from(3u32)));
+    }
+
+    #[test]
+    fn day14_spin_repetitions() {
+        let input = input_generator_bits(TEST_INPUT).unwrap();
+        let outcome = spin(&input, &SPIN_CYCLE, &BigUint::from(0u32));
+        assert_eq!(outcome.platform, input);
+        assert_eq!(outcome.north_load, 104);
+        assert_eq!(spin(&input, &SPIN_CYCLE, &BigUint::from(1_000_000_000u64)).north_load, 64);
+        /* 10^30 = 3 + 7k + 5 */
+        let huge = BigUint::from(10u32).pow(30);
+        let expected = SpinHistory::new(&input, &SPIN_CYCLE).state_after(&BigUint::from(8u32));
+        assert_eq!(spin(&input, &SPIN_CYCLE, &huge).platform, expected);
+    }
+
+    #[test]
+    fn day14_spin_custom_sequence() {
+        let input = input_generator_bits(TEST_INPUT).unwrap();
+        let outcome = spin(&input, &[Tilt::North], &BigUint::from(5u32));
+        assert_eq!(outcome.north_load, 136);
+        let outcome = spin(&input, &[], &BigUint::from(5u32));
+        assert_eq!(outcome.platform, input);
+        let outcome = spin(&input, &[Tilt::East, Tilt::South], &BigUint::from(3u32));
+        let mut expected = input.clone();
+        for _ in 0..3 {
+            expected.tilt_east();
+            expected.tilt_south();
+        }
+        assert_eq!(outcome.platform, expected);
+        assert_eq!(outcome.load(Tilt::South), outcome.platform.load(Tilt::South));
+    }
+
+    #[test]
+    fn day14_load_sides() {
+        let mut platform = BitPlatform::new(&input_generator("O.\n.O\n#.")).unwrap();
+        assert_eq!(platform.load(Tilt::North), 3 + 2);
+        assert_eq!(platform.load(Tilt::South), 1 + 2);
+        assert_eq!(platform.load(Tilt::West), 2 + 1);
+        assert_eq!(platform.load(Tilt::East), 1 + 2);
+        platform.tilt(Tilt::East);
+        assert_eq!(platform.load(Tilt::East), 2 + 2);
+    }
 }
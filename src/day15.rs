@@ -1,23 +1,56 @@
-use std::collections::HashMap;
-use nom::{
-    Parser,
-    IResult,
-    branch::alt,
-    combinator::{all_consuming, value},
-    bytes::complete::{tag, take_while1},
-    multi::separated_list1,
-    sequence::{tuple, preceded},
-};
+use std::fmt::{self, Display};
 
 #[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
-enum Instruction {
+pub enum Instruction {
     Assign(usize),
     Remove
 }
 
-struct LensInstruction {
-    instruction: Instruction,
-    lens_label: String,
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct LensInstruction {
+    pub instruction: Instruction,
+    pub lens_label: String,
+}
+
+impl Display for LensInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.instruction {
+            Instruction::Assign(focal_length) => write!(f, "{}={}", self.lens_label, focal_length),
+            Instruction::Remove => write!(f, "{}-", self.lens_label),
+        }
+    }
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum StepError {
+    EmptyLabel,
+    MissingOperation,
+    MissingFocalLength,
+    FocalLengthOutOfRange(usize),
+    FocalLengthOverflow(String),
+    UnexpectedInput(String),
+}
+
+/* Malformed step of an initialization sequence, index counts the comma-separated steps from 0 */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct InvalidStep {
+    pub index: usize,
+    pub step: String,
+    pub error: StepError,
+}
+
+impl Display for InvalidStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Step {} \"{}\": ", self.index, self.step)?;
+        match &self.error {
+            StepError::EmptyLabel => write!(f, "empty label"),
+            StepError::MissingOperation => write!(f, "missing '=' or '-'"),
+            StepError::MissingFocalLength => write!(f, "missing focal length"),
+            StepError::FocalLengthOutOfRange(focal_length) => write!(f, "focal length {} is outside 1-9", focal_length),
+            StepError::FocalLengthOverflow(digits) => write!(f, "focal length {} doesn't fit into usize", digits),
+            StepError::UnexpectedInput(rest) => write!(f, "unexpected \"{}\"", rest),
+        }
+    }
 }
 
 #[aoc(day15, part1)]
@@ -31,109 +64,177 @@ pub fn solve_part1(input: &str) -> u64 {
 }
 
 fn parse_hash_instruction(input: &str) -> Vec<LensInstruction> {
-    match all_consuming(separated_list1(tag(","), parse_lens_instruction))
-        .parse(input) {
-            Ok((_, val)) => val,
-            Err(e) => panic!("{}", e),
-        }
+    match parse_initialization_sequence(input) {
+        Ok(val) => val,
+        Err(errors) => panic!("{}", errors.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n")),
+    }
 }
 
-fn parse_lens_instruction(input: &str) -> IResult<&str, LensInstruction> {
-    tuple((parse_label,parse_operation))
-        .map(|(p_label, p_op)| LensInstruction{instruction: p_op, lens_label: p_label})
-        .parse(input)
+/* Parses every step and reports all malformed ones */
+pub fn parse_initialization_sequence(input: &str) -> Result<Vec<LensInstruction>, Vec<InvalidStep>> {
+    let mut instructions = Vec::<LensInstruction>::new();
+    let mut errors = Vec::<InvalidStep>::new();
+    for (index, step) in input.trim_end_matches('\n').split(',').enumerate() {
+        match parse_lens_instruction(step) {
+            Ok(instr) => instructions.push(instr),
+            Err(error) => errors.push(InvalidStep {index, step: step.to_string(), error}),
+        }
+    }
+    if errors.is_empty() { Ok(instructions) } else { Err(errors) }
 }
 
-fn parse_label(input: &str) -> IResult<&str, String> {
-    take_while1(char::is_alphabetic)
-        .map(|str: &str| str.to_string())
-        .parse(input)
+fn parse_lens_instruction(step: &str) -> Result<LensInstruction, StepError> {
+    let (rest, (label, operation, digits)) = split_step(step);
+    if label.is_empty() {
+        return Err(StepError::EmptyLabel);
+    }
+    let instruction = match (operation, digits) {
+        (None, _) => return Err(StepError::MissingOperation),
+        (Some('='), "") => return Err(StepError::MissingFocalLength),
+        (Some('='), digits) => match digits.parse::<usize>() {
+            Ok(focal_length @ 1..=9) => Instruction::Assign(focal_length),
+            Ok(focal_length) => return Err(StepError::FocalLengthOutOfRange(focal_length)),
+            Err(_) => return Err(StepError::FocalLengthOverflow(digits.to_string())),
+        },
+        (_, "") => Instruction::Remove,
+        (_, digits) => return Err(StepError::UnexpectedInput(digits.to_string() + rest)),
+    };
+    if !rest.is_empty() {
+        return Err(StepError::UnexpectedInput(rest.to_string()));
+    }
+    Ok(LensInstruction {instruction, lens_label: label.to_string()})
 }
 
-fn parse_operation(input: &str) -> IResult<&str, Instruction> {
-    alt((
-            preceded(tag("="), parse_lens_assignment),
-            value(Instruction::Remove, tag("-")),
-            ))
-        .parse(input)
+/* label, '=' or '-', digits; every part may be missing so errors can say which one is */
+fn split_step(input: &str) -> (&str, (&str, Option<char>, &str)) {
+    let (label, rest) = input.split_at(input.find(|c: char| !c.is_alphabetic()).unwrap_or(input.len()));
+    let (operation, rest) = match rest.chars().next() {
+        Some(op @ ('=' | '-')) => (Some(op), &rest[1..]),
+        _ => (None, rest),
+    };
+    let (digits, rest) = rest.split_at(rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len()));
+    (rest, (label, operation, digits))
 }
 
-fn parse_lens_assignment(input: &str) -> IResult<&str, Instruction> {
-    parse_num_to_usize
-        .map(|num| Instruction::Assign(num))
-        .parse(input)
+fn label_to_register_num(label: &str) -> usize {
+    label.bytes()
+        .fold(0usize, |acc,el| ((acc + el as usize) * 17) % 256)
 }
 
-fn parse_num_to_usize(input: &str) -> IResult<&str, usize> {
-    take_while1(char::is_numeric)
-        .map(|num: &str| num.parse::<usize>().unwrap())
-        .parse(input)
+/* The HASHMAP from part 2: 256 boxes of lenses, the box is picked by the HASH of the label */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct LensHashMap {
+    boxes: Vec<Vec<(String,usize)>>,
 }
 
-fn label_to_register_num(label: &str) -> usize {
-    label.bytes()
-        .fold(0usize, |acc,el| ((acc + el as usize) * 17) % 256)
+impl Default for LensHashMap {
+    fn default() -> Self {
+        LensHashMap {boxes: vec![vec![]; 256]}
+    }
 }
 
-/* Box number from part 1 */
-fn hashmap_protocoll_for_p2<'a>(instructions: &'a [LensInstruction]) -> Vec<Vec<(&'a str,usize)>> {
-    let label_locs = instructions.iter()
-        .map(|el| (el.lens_label.as_str(), label_to_register_num(&el.lens_label)))
-        .collect::<HashMap<&str, usize>>();
-    let mut registers: Vec<Vec<(&str,usize)>> = vec![vec![]; 256];
-    for instr in instructions.iter() {
-        match instr.instruction {
-            Instruction::Assign(focal_length) => {
-                let reg_num = label_locs.get(instr.lens_label.as_str()).unwrap();
-                let curr_register = &mut registers[*reg_num];
-                if curr_register.iter().any(|(el,_)| *el == instr.lens_label.as_str()) {
-                    /* search and replace */
-                    let idx = curr_register
-                        .iter()
-                        .enumerate()
-                        .filter(|(_,(label,_))| *label == instr.lens_label.as_str())
-                        .map(|(num,_)| num)
-                        .next().unwrap();
-                    curr_register[idx] = (instr.lens_label.as_str(), focal_length);
-                } else {
-                    registers[*reg_num].push((instr.lens_label.as_str(), focal_length));
-                }
-            },
-            Instruction::Remove => {
-                /* remove if in box */
-                let reg_num = label_locs.get(instr.lens_label.as_str()).unwrap();
-                let curr_register = &mut registers[*reg_num];
-                if curr_register.iter().any(|(label,_)| *label == instr.lens_label) {
-                    let idx = curr_register
-                        .iter()
-                        .enumerate()
-                        .filter(|(_, (label,_))| *label == instr.lens_label)
-                        .map(|(num,_)| num)
-                        .next().unwrap();
-                    curr_register.remove(idx);
-                }
+impl LensHashMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Replaces the lens with the same label in place or adds it to the back of its box.
+     * Returns the replaced focal length. */
+    pub fn insert(&mut self, label: &str, focal_length: usize) -> Option<usize> {
+        let curr_box = &mut self.boxes[label_to_register_num(label)];
+        match curr_box.iter_mut().find(|(el,_)| el == label) {
+            Some((_, old)) => Some(std::mem::replace(old, focal_length)),
+            None => {
+                curr_box.push((label.to_string(), focal_length));
+                None
             },
         }
     }
-    registers
+
+    /* Takes the lens out of its box, the lenses behind it move forward */
+    pub fn remove(&mut self, label: &str) -> Option<usize> {
+        let curr_box = &mut self.boxes[label_to_register_num(label)];
+        let idx = curr_box.iter().position(|(el,_)| el == label)?;
+        Some(curr_box.remove(idx).1)
+    }
+
+    pub fn get(&self, label: &str) -> Option<usize> {
+        self.boxes[label_to_register_num(label)].iter()
+            .find(|(el,_)| el == label)
+            .map(|(_,focal_length)| *focal_length)
+    }
+
+    pub fn lenses_in_box(&self, box_num: usize) -> &[(String,usize)] {
+        &self.boxes[box_num]
+    }
+
+    /* (box number, slot, label, focal length) in box and slot order */
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &str, usize)> {
+        self.boxes.iter()
+            .enumerate()
+            .flat_map(|(box_num, lenses)| {
+                lenses.iter()
+                    .enumerate()
+                    .map(move |(slot, (label, focal_length))| (box_num, slot, label.as_str(), *focal_length))
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.boxes.iter().map(|lenses| lenses.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.iter().all(|lenses| lenses.is_empty())
+    }
+
+    fn apply(&mut self, instr: &LensInstruction) {
+        match instr.instruction {
+            Instruction::Assign(focal_length) => { self.insert(&instr.lens_label, focal_length); },
+            Instruction::Remove => { self.remove(&instr.lens_label); },
+        }
+    }
+
+    pub fn focusing_power(&self) -> u64 {
+        self.iter()
+            .map(|(box_num, slot, _, focal_length)| ((box_num + 1) * (slot + 1) * focal_length) as u64)
+            .sum()
+    }
 }
 
-fn calc_lens_power(registers: Vec<Vec<(&str,usize)>>) -> u64 {
-    registers.into_iter()
-        .enumerate()
-        .map(|(box_num,vec)| (box_num + 1, vec))
-        .map(|(box_num,vec)| {
-            let num = box_num * vec.into_iter().enumerate().map(|(i,(_,num))| num * (i + 1)).sum::<usize>();
-            num
+/* Non-empty boxes like the puzzle's "Box 0: [rn 1] [cm 2]" */
+impl Display for LensHashMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (box_num, lenses) in self.boxes.iter().enumerate().filter(|(_,lenses)| !lenses.is_empty()) {
+            write!(f, "Box {}:", box_num)?;
+            for (label, focal_length) in lenses.iter() {
+                write!(f, " [{} {}]", label, focal_length)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/* Box state after every instruction */
+pub fn trace_initialization(instructions: &[LensInstruction]) -> impl Iterator<Item = LensHashMap> + '_ {
+    instructions.iter()
+        .scan(LensHashMap::new(), |registers, instr| {
+            registers.apply(instr);
+            Some(registers.clone())
         })
-    .sum::<usize>() as u64
+}
+
+fn hashmap_protocoll_for_p2(instructions: &[LensInstruction]) -> LensHashMap {
+    let mut registers = LensHashMap::new();
+    instructions.iter().for_each(|instr| registers.apply(instr));
+    registers
 }
 
 #[aoc(day15, part2)]
 pub fn solve_part2(input: &str) -> u64 {
     let instrs = parse_hash_instruction(input);
     let registers = hashmap_protocoll_for_p2(&instrs);
-    calc_lens_power(registers)
+    registers.focusing_power()
 }
 
 #[cfg(test)]
@@ -151,11 +252,11 @@ mod tests {
     fn day15_hashmap_protocoll() {
         let input = parse_hash_instruction(TEST_INPUT);
         let ans = hashmap_protocoll_for_p2(&input);
-        assert_eq!(ans.len(), 256);
-        assert_eq!(ans[0].len(), 2);
-        assert_eq!(ans[3].len(), 3);
-        assert_eq!(ans[0][0], ("rn", 1));
-        assert_eq!(ans[3][1], ("ab", 5));
+        assert_eq!(ans.len(), 5);
+        assert_eq!(ans.lenses_in_box(0).len(), 2);
+        assert_eq!(ans.lenses_in_box(3).len(), 3);
+        assert_eq!(ans.lenses_in_box(0)[0], ("rn".to_string(), 1));
+        assert_eq!(ans.lenses_in_box(3)[1], ("ab".to_string(), 5));
     }
     
     #[test]
@@ -163,4 +264,104 @@ mod tests {
         let ans = solve_part2(TEST_INPUT);
         assert_eq!(ans, 145);
     }
+
+    #[test]
+    fn day15_replay_example() {
+        let expected = r#"After "rn=1":
+Box 0: [rn 1]
+
+After "cm-":
+Box 0: [rn 1]
+
+After "qp=3":
+Box 0: [rn 1]
+Box 1: [qp 3]
+
+After "cm=2":
+Box 0: [rn 1] [cm 2]
+Box 1: [qp 3]
+
+After "qp-":
+Box 0: [rn 1] [cm 2]
+
+After "pc=4":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4]
+
+After "ot=9":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4] [ot 9]
+
+After "ab=5":
+Box 0: [rn 1] [cm 2]
+Box 3: [pc 4] [ot 9] [ab 5]
+
+After "pc-":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 9] [ab 5]
+
+After "pc=6":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 9] [ab 5] [pc 6]
+
+After "ot=7":
+Box 0: [rn 1] [cm 2]
+Box 3: [ot 7] [ab 5] [pc 6]
+"#;
+        let instructions = parse_hash_instruction(TEST_INPUT);
+        let replay = instructions.iter()
+            .zip(trace_initialization(&instructions))
+            .map(|(instr, registers)| format!("After \"{}\":\n{}", instr, registers))
+            .collect::<Vec<String>>();
+        assert_eq!(replay.join("\n"), expected);
+        assert_eq!(trace_initialization(&instructions).last().unwrap().focusing_power(), 145);
+    }
+
+    #[test]
+    fn day15_lens_hashmap() {
+        let mut registers = LensHashMap::new();
+        assert!(registers.is_empty());
+        assert_eq!(registers.insert("rn", 1), None);
+        assert_eq!(registers.insert("cm", 2), None);
+        assert_eq!(registers.insert("rn", 5), Some(1));
+        assert_eq!(registers.get("rn"), Some(5));
+        assert_eq!(registers.get("qp"), None);
+        assert_eq!(registers.iter().collect::<Vec<_>>(), vec![(0, 0, "rn", 5), (0, 1, "cm", 2)]);
+        assert_eq!(registers.remove("rn"), Some(5));
+        assert_eq!(registers.remove("rn"), None);
+        assert_eq!(registers.iter().collect::<Vec<_>>(), vec![(0, 0, "cm", 2)]);
+        assert_eq!(registers.focusing_power(), 2);
+        assert_eq!(registers.to_string(), "Box 0: [cm 2]\n");
+    }
+
+    #[test]
+    fn day15_validate_sequence() {
+        let errors = parse_initialization_sequence("rn=1,cm=,=3,qp=0,ab=12,pc,ot-4,xy=5z,ok-,ef=99999999999999999999999").unwrap_err();
+        let summary = errors.iter()
+            .map(|e| (e.index, e.error.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            (1, StepError::MissingFocalLength),
+            (2, StepError::EmptyLabel),
+            (3, StepError::FocalLengthOutOfRange(0)),
+            (4, StepError::FocalLengthOutOfRange(12)),
+            (5, StepError::MissingOperation),
+            (6, StepError::UnexpectedInput("4".to_string())),
+            (7, StepError::UnexpectedInput("z".to_string())),
+            (9, StepError::FocalLengthOverflow("99999999999999999999999".to_string())),
+        ]);
+        assert_eq!(errors[0].to_string(), "Step 1 \"cm=\": missing focal length");
+        assert_eq!(errors[3].to_string(), "Step 4 \"ab=12\": focal length 12 is outside 1-9");
+        assert_eq!(errors[7].to_string(),
+            "Step 9 \"ef=99999999999999999999999\": focal length 99999999999999999999999 doesn't fit into usize");
+        assert_eq!(parse_initialization_sequence(",rn-").unwrap_err()[0].error, StepError::EmptyLabel);
+    }
+
+    #[test]
+    fn day15_valid_sequence() {
+        let instructions = parse_initialization_sequence(TEST_INPUT).unwrap();
+        assert_eq!(instructions.len(), 11);
+        assert_eq!(instructions[0], LensInstruction {instruction: Instruction::Assign(1), lens_label: "rn".to_string()});
+        assert_eq!(instructions.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(","), TEST_INPUT);
+    }
 }
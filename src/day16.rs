@@ -1,27 +1,23 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use petgraph::{
+    algo::{condensation, toposort},
     graphmap::DiGraphMap,
     visit::Bfs,
+    Direction,
 };
+use ndarray::Array2;
 use rayon::prelude::*;
 
-#[derive(Clone,Copy,Debug)]
-enum Optics {
-    MirrorSlash,      // /
-    MirrorBackslash,  // \
-    VertSplitter,     // |
-    HorzSplitter,     // -
-    Open,             // .
-}
-
 #[derive(Clone,Copy,Debug,Hash,PartialEq,Eq,PartialOrd,Ord)]
 pub struct MirrorNode {
     coords: (usize,usize),
     beam_from: IncidenceDirection,
 }
 
+/* Side of a tile the beam enters from */
 #[derive(Clone,Copy,Debug,Hash,PartialEq,Eq,PartialOrd,Ord)]
-enum IncidenceDirection {
+pub enum IncidenceDirection {
     Above,
     Below,
     Left,
@@ -29,55 +25,198 @@ enum IncidenceDirection {
     Source,
 }
 
+/* Behaviour of one optical element: for every side the beam can enter from, the sides the
+ * outgoing beams enter the next tiles from. E.g. '/' maps Left to [Below], because a beam
+ * travelling right is sent up and enters the tile above from below. */
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct OpticElement {
+    outgoing: [Vec<IncidenceDirection>; 4],//indexed by Above, Below, Left, Right
+}
+
+impl OpticElement {
+    /* Mappings come from OpticsTable::with_element, which rejects Source */
+    fn new(mapping: &[(IncidenceDirection, &[IncidenceDirection])]) -> Self {
+        let mut element = OpticElement::default();
+        for (incoming, outgoing) in mapping.iter() {
+            if let Some(slot) = Self::slot(*incoming) {
+                element.outgoing[slot] = outgoing.to_vec();
+            }
+        }
+        element
+    }
+
+    /* Source is not a side of a tile, so nothing leaves towards it */
+    pub fn outgoing(&self, incoming: IncidenceDirection) -> &[IncidenceDirection] {
+        Self::slot(incoming).map_or(&[], |slot| &self.outgoing[slot])
+    }
+
+    #[inline]
+    fn slot(dir: IncidenceDirection) -> Option<usize> {
+        match dir {
+            IncidenceDirection::Above => Some(0),
+            IncidenceDirection::Below => Some(1),
+            IncidenceDirection::Left => Some(2),
+            IncidenceDirection::Right => Some(3),
+            IncidenceDirection::Source => None,
+        }
+    }
+}
+
+/* Table from input character to optical element */
+#[derive(Clone,Debug,Default)]
+pub struct OpticsTable {
+    elements: HashMap<char, OpticElement>,
+}
+
+impl OpticsTable {
+    /* The puzzle's '.', '/', '\', '|' and '-' */
+    pub fn puzzle() -> Self {
+        use IncidenceDirection::*;
+        let table = || -> Result<Self, SourceInOpticError> {
+            OpticsTable::default()
+                .with_element('.', &[
+                    (Above, &[Above]), (Below, &[Below]), (Left, &[Left]), (Right, &[Right])])?
+                .with_element('/', &[
+                    (Left, &[Below]), (Right, &[Above]), (Above, &[Right]), (Below, &[Left])])?
+                .with_element('\\', &[
+                    (Left, &[Above]), (Right, &[Below]), (Above, &[Left]), (Below, &[Right])])?
+                .with_element('|', &[
+                    (Left, &[Above, Below]), (Right, &[Above, Below]), (Above, &[Above]), (Below, &[Below])])?
+                .with_element('-', &[
+                    (Below, &[Right, Left]), (Above, &[Right, Left]), (Left, &[Left]), (Right, &[Right])])
+        };
+        table().expect("The puzzle's optics don't use Source")
+    }
+
+    /* Source only marks where a beam starts, it's neither a side a beam enters from nor one it leaves to */
+    pub fn with_element(mut self, ch: char, mapping: &[(IncidenceDirection, &[IncidenceDirection])])
+        -> Result<Self, SourceInOpticError>
+    {
+        let is_source = |dir: &IncidenceDirection| *dir == IncidenceDirection::Source;
+        if mapping.iter().any(|(incoming, outgoing)| is_source(incoming) || outgoing.iter().any(is_source)) {
+            return Err(SourceInOpticError {ch});
+        }
+        self.elements.insert(ch, OpticElement::new(mapping));
+        Ok(self)
+    }
+
+    pub fn get(&self, ch: char) -> Option<&OpticElement> {
+        self.elements.get(&ch)
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct UnknownOpticError {
+    pub line: usize,//1-based like an editor
+    pub column: usize,
+    pub ch: char,
+}
+
+impl fmt::Display for UnknownOpticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unexpected char '{}' at line {}, column {}", self.ch, self.line, self.column)
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum MirrorsError {
+    UnknownOptic(UnknownOpticError),
+    EmptyGrid,
+}
+
+impl From<UnknownOpticError> for MirrorsError {
+    fn from(err: UnknownOpticError) -> Self {
+        MirrorsError::UnknownOptic(err)
+    }
+}
+
+impl fmt::Display for MirrorsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MirrorsError::UnknownOptic(err) => write!(f, "{}", err),
+            MirrorsError::EmptyGrid => write!(f, "Empty grid"),
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct SourceInOpticError {
+    pub ch: char,
+}
+
+impl fmt::Display for SourceInOpticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Optic '{}' uses Source as a side of its tile", self.ch)
+    }
+}
+
 #[derive(Clone,Debug)]
 pub struct Mirrors {
     graph: DiGraphMap<MirrorNode,()>,
+    optics: Vec<Vec<char>>,
     outer_max: usize,
     inner_max: usize,
 }
 
+/* Energized tiles and the directions the beams travelled through each tile */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct BeamTrace {
+    pub energized: Array2<bool>,
+    pub directions: Array2<u8>,//bitmask of BEAM_RIGHT, BEAM_LEFT, BEAM_DOWN, BEAM_UP
+}
+
+pub const BEAM_RIGHT: u8 = 0b0001;
+pub const BEAM_LEFT: u8 = 0b0010;
+pub const BEAM_DOWN: u8 = 0b0100;
+pub const BEAM_UP: u8 = 0b1000;
+
 #[aoc_generator(day16)]
 pub fn input_generator(input: &str) -> Mirrors {
-    let optics_fixtures = input_to_optics(input);
-    let out_graph = build_mirror_graph(&optics_fixtures);
-    Mirrors {graph: out_graph, outer_max: optics_fixtures.len() + 1, inner_max: optics_fixtures[0].len() + 1}
+    match input_generator_with_table(input, &OpticsTable::puzzle()) {
+        Ok(mirrors) => mirrors,
+        Err(e) => panic!("While parsing : {}", e),
+    }
+}
+
+pub fn input_generator_with_table(input: &str, table: &OpticsTable) -> Result<Mirrors, MirrorsError> {
+    let optics_fixtures = input_to_optics(input, table)?;
+    let inner_max = match optics_fixtures.first() {
+        Some(line) if !line.is_empty() => line.len() + 1,
+        _ => return Err(MirrorsError::EmptyGrid),
+    };
+    let out_graph = build_mirror_graph(&optics_fixtures, table);
+    let outer_max = optics_fixtures.len() + 1;
+    Ok(Mirrors {graph: out_graph, optics: optics_fixtures, outer_max, inner_max})
 }
 
-fn input_to_optics(input: &str) -> Vec<Vec<Optics>> {
+fn input_to_optics(input: &str, table: &OpticsTable) -> Result<Vec<Vec<char>>, UnknownOpticError> {
     input.lines()
-        .map(|line| {
-            line
-                .bytes()
-                .map(|c| match c {
-                    b'|' => Optics::VertSplitter,
-                    b'/' => Optics::MirrorSlash,
-                    b'\\' => Optics::MirrorBackslash,
-                    b'-' => Optics::HorzSplitter,
-                    b'.' => Optics::Open,
-                    _ => unreachable!("Unexpected char")
+        .enumerate()
+        .map(|(i,line)| {
+            line.chars()
+                .enumerate()
+                .map(|(j,ch)| match table.get(ch) {
+                    Some(_) => Ok(ch),
+                    None => Err(UnknownOpticError {line: i + 1, column: j + 1, ch}),
                 })
             .collect()
         })
     .collect()
 }
 
-fn build_mirror_graph(optics: &Vec<Vec<Optics>>) -> DiGraphMap<MirrorNode,()> {
+fn build_mirror_graph(optics: &[Vec<char>], table: &OpticsTable) -> DiGraphMap<MirrorNode,()> {
     let outer_max = optics.len();
     let inner_max = optics[0].len();
     let mut out_graph = DiGraphMap::<MirrorNode,()>::new();
     for (i,line) in optics.iter().enumerate() {
-        for (j,val) in line.iter().enumerate() {
+        for (j,ch) in line.iter().enumerate() {
             /* Shift the grid and surround it with source-nodes at i=0, j=0, i=outer_max+1,
             * j=inner_max+1*/
             let i_new = i+1;
             let j_new = j+1;
-            match *val {
-                Optics::MirrorBackslash => connect_edges_to_mirror_backslash(&mut out_graph, i_new, j_new, outer_max, inner_max),
-                Optics::MirrorSlash => connect_edges_to_mirror_slash(&mut out_graph, i_new, j_new, outer_max, inner_max),
-                Optics::VertSplitter => connect_edges_to_vert_splitter(&mut out_graph, i_new, j_new, outer_max, inner_max),
-                Optics::HorzSplitter => connect_edges_to_horz_splitter(&mut out_graph, i_new, j_new, outer_max, inner_max),
-                Optics::Open => connect_edges_to_open(&mut out_graph, i_new, j_new, outer_max, inner_max),
-            }
+            connect_to_source(&mut out_graph, i_new, j_new, outer_max, inner_max);
+            let element = table.get(*ch).expect("Optics are checked while parsing");
+            connect_edges_to_element(&mut out_graph, element, i_new, j_new);
         }
     }
     out_graph
@@ -107,129 +246,25 @@ fn connect_to_source(graph: &mut DiGraphMap<MirrorNode,()>, i: usize, j: usize,
     }
 }
 
-fn connect_edges_to_mirror_slash(graph: &mut DiGraphMap<MirrorNode,()>, i: usize, j: usize, outer_max: usize, inner_max: usize) {
-    connect_to_source(graph, i, j, outer_max, inner_max);
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Left },
-        MirrorNode { coords: (i-1,j), beam_from: IncidenceDirection::Below },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Right },
-        MirrorNode { coords: (i+1,j), beam_from: IncidenceDirection::Above },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Above },
-        MirrorNode { coords: (i,j-1), beam_from: IncidenceDirection::Right },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Below },
-        MirrorNode { coords: (i,j+1), beam_from: IncidenceDirection::Left },
-        ());
-}
-
-fn connect_edges_to_mirror_backslash(graph: &mut DiGraphMap<MirrorNode,()>, i: usize, j: usize, outer_max: usize, inner_max: usize) {
-    connect_to_source(graph, i, j, outer_max, inner_max);
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Left },
-        MirrorNode { coords: (i+1,j), beam_from: IncidenceDirection::Above },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Right },
-        MirrorNode { coords: (i-1,j), beam_from: IncidenceDirection::Below },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Above },
-        MirrorNode { coords: (i,j+1), beam_from: IncidenceDirection::Left },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Below },
-        MirrorNode { coords: (i,j-1), beam_from: IncidenceDirection::Right },
-        ());
-}
-
-fn connect_edges_to_vert_splitter(graph: &mut DiGraphMap<MirrorNode,()>, i: usize, j: usize, outer_max: usize, inner_max: usize) {
-    connect_to_source(graph, i, j, outer_max, inner_max);
-    /*Split when coming from left */
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Left },
-        MirrorNode { coords: (i+1,j), beam_from: IncidenceDirection::Above },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Left },
-        MirrorNode { coords: (i-1,j), beam_from: IncidenceDirection::Below },
-        ());
-    /*Split when coming from right */
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Right },
-        MirrorNode { coords: (i+1,j), beam_from: IncidenceDirection::Above },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Right },
-        MirrorNode { coords: (i-1,j), beam_from: IncidenceDirection::Below },
-        ());
-    /* Let it pass straight through when coming from above or below */
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Above },
-        MirrorNode { coords: (i+1,j), beam_from: IncidenceDirection::Above },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Below },
-        MirrorNode { coords: (i-1,j), beam_from: IncidenceDirection::Below },
-        ());
-}
-
-fn connect_edges_to_horz_splitter(graph: &mut DiGraphMap<MirrorNode,()>, i: usize, j: usize, outer_max: usize, inner_max: usize) {
-    connect_to_source(graph, i, j, outer_max, inner_max);
-    /*Split when coming from below */
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Below },
-        MirrorNode { coords: (i,j-1), beam_from: IncidenceDirection::Right },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Below },
-        MirrorNode { coords: (i,j+1), beam_from: IncidenceDirection::Left },
-        ());
-    /*Split when coming from above */
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Above },
-        MirrorNode { coords: (i,j-1), beam_from: IncidenceDirection::Right },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Above },
-        MirrorNode { coords: (i,j+1), beam_from: IncidenceDirection::Left },
-        ());
-    /* Let it pass straight through when coming from the left or right */
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Left },
-        MirrorNode { coords: (i,j+1), beam_from: IncidenceDirection::Left },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Right },
-        MirrorNode { coords: (i,j-1), beam_from: IncidenceDirection::Right },
-        ());
-}
-
-fn connect_edges_to_open(graph: &mut DiGraphMap<MirrorNode,()>, i: usize, j: usize, outer_max: usize, inner_max: usize) {
-    connect_to_source(graph, i, j, outer_max, inner_max);
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Below },
-        MirrorNode { coords: (i-1,j), beam_from: IncidenceDirection::Below },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Above },
-        MirrorNode { coords: (i+1,j), beam_from: IncidenceDirection::Above },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Left },
-        MirrorNode { coords: (i,j+1), beam_from: IncidenceDirection::Left },
-        ());
-    graph.add_edge(
-        MirrorNode { coords: (i,j), beam_from: IncidenceDirection::Right },
-        MirrorNode { coords: (i,j-1), beam_from: IncidenceDirection::Right },
-        ());
+fn connect_edges_to_element(graph: &mut DiGraphMap<MirrorNode,()>, element: &OpticElement, i: usize, j: usize) {
+    use IncidenceDirection::*;
+    for incoming in [Above, Below, Left, Right] {
+        for outgoing in element.outgoing(incoming).iter() {
+            let next_coords = match outgoing {
+                Above => (i+1,j),
+                Below => (i-1,j),
+                Left => (i,j+1),
+                Right => (i,j-1),
+                Source => unreachable!("OpticsTable rejects optics leaving towards Source"),
+            };
+            graph.add_edge(
+                MirrorNode { coords: (i,j), beam_from: incoming },
+                MirrorNode { coords: next_coords, beam_from: *outgoing },
+                ());
+        }
+    }
 }
 
-
 fn count_illuminated_tiles(gr: &DiGraphMap<MirrorNode,()>, om: usize, im: usize, start_node: MirrorNode) -> usize {
     let mut bfs = Bfs::new(gr, start_node);
     let mut illuminated = HashSet::<(usize,usize)>::new();
@@ -247,16 +282,148 @@ fn count_illuminated_tiles(gr: &DiGraphMap<MirrorNode,()>, om: usize, im: usize,
         .count()
 }
 
+/* Follows the beam from a source node and records which tiles it energizes and in which
+ * directions it passes through them */
+pub fn trace_beam(input: &Mirrors, source_coords: (usize,usize)) -> BeamTrace {
+    let Mirrors {graph: gr, optics: _, outer_max: om, inner_max: im} = input;
+    let shape = (om - 1, im - 1);
+    let mut directions = Array2::<u8>::zeros(shape);
+    let start_node = MirrorNode { coords: source_coords, beam_from: IncidenceDirection::Source };
+    if gr.contains_node(start_node) {
+        let mut bfs = Bfs::new(gr, start_node);
+        while let Some(mnode) = bfs.next(gr) {
+            let (i,j) = mnode.coords;
+            if tile_bit(mnode.coords, *om, *im).is_none() {
+                continue;
+            }
+            directions[(i - 1, j - 1)] |= match mnode.beam_from {
+                IncidenceDirection::Left => BEAM_RIGHT,
+                IncidenceDirection::Right => BEAM_LEFT,
+                IncidenceDirection::Above => BEAM_DOWN,
+                IncidenceDirection::Below => BEAM_UP,
+                IncidenceDirection::Source => 0,
+            };
+        }
+    }
+    let energized = directions.map(|dirs| *dirs != 0);
+    BeamTrace {energized, directions}
+}
+
+impl BeamTrace {
+    pub fn count(&self) -> usize {
+        self.energized.iter().filter(|e| **e).count()
+    }
+
+    /* The puzzle's map of energized (#) and dark (.) tiles */
+    pub fn render_energized(&self) -> String {
+        self.energized.rows().into_iter()
+            .map(|row| row.iter().map(|e| if *e { '#' } else { '.' }).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /* The puzzle's beam view: optics stay as they are, open tiles show the beam's direction
+     * or the number of beams if more than one passed through */
+    pub fn render_directions(&self, input: &Mirrors) -> String {
+        input.optics.iter()
+            .enumerate()
+            .map(|(i,line)| {
+                line.iter()
+                    .enumerate()
+                    .map(|(j,ch)| {
+                        let dirs = self.directions[(i,j)];
+                        match (ch, dirs) {
+                            ('.', BEAM_RIGHT) => '>',
+                            ('.', BEAM_LEFT) => '<',
+                            ('.', BEAM_DOWN) => 'v',
+                            ('.', BEAM_UP) => '^',
+                            ('.', 0) => '.',
+                            ('.', _) => char::from_digit(dirs.count_ones(), 10).unwrap(),
+                            _ => *ch,
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/* Tile bitset over the grid without the source border */
+#[inline]
+fn tile_bit(coords: (usize,usize), outer_max: usize, inner_max: usize) -> Option<usize> {
+    let (i,j) = coords;
+    if (i == 0) || (j == 0) || (i >= outer_max) || (j >= inner_max) {
+        return None;
+    }
+    Some((i - 1) * (inner_max - 1) + (j - 1))
+}
+
+/* Condenses the mirror graph into its strongly connected components (splitters create loops)
+ * and combines the energized tiles along the resulting DAG in reverse topological order. A
+ * component's tile set is dropped once all components leading into it have used it.
+ * Returns every source node coords with the number of tiles its beam energizes. */
+pub fn count_illuminated_tiles_all_entries(input: &Mirrors) -> Vec<((usize,usize), usize)> {
+    let Mirrors {graph: gr, optics: _, outer_max: om, inner_max: im} = input;
+    let words = ((om - 1) * (im - 1)).div_ceil(64);
+    let condensed = condensation(gr.clone().into_graph::<u32>(), true);
+    let order = toposort(&condensed, None).expect("Condensed graph is acyclic");
+    let mut parents_left = condensed.node_indices()
+        .map(|scc| condensed.neighbors_directed(scc, Direction::Incoming).count())
+        .collect::<Vec<usize>>();
+    let mut tile_sets: Vec<Option<Vec<u64>>> = vec![None; condensed.node_count()];
+    let mut out = Vec::<((usize,usize), usize)>::new();
+    for scc in order.into_iter().rev() {
+        let mut tiles = vec![0u64; words];
+        for mnode in condensed[scc].iter() {
+            if let Some(bit) = tile_bit(mnode.coords, *om, *im) {
+                tiles[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        for child in condensed.neighbors_directed(scc, Direction::Outgoing) {
+            let child_idx = child.index();
+            let child_tiles = tile_sets[child_idx].as_ref().expect("Children are visited first");
+            tiles.iter_mut()
+                .zip(child_tiles.iter())
+                .for_each(|(t, c)| *t |= c);
+            parents_left[child_idx] -= 1;
+            if parents_left[child_idx] == 0 {
+                tile_sets[child_idx] = None;
+            }
+        }
+        for mnode in condensed[scc].iter().filter(|mnode| mnode.beam_from == IncidenceDirection::Source) {
+            out.push((mnode.coords, tiles.iter().map(|t| t.count_ones() as usize).sum()));
+        }
+        if parents_left[scc.index()] > 0 {
+            tile_sets[scc.index()] = Some(tiles);
+        }
+    }
+    out.sort_unstable();
+    out
+}
+
+/* Entry point (source node coords) that energizes the most tiles */
+pub fn best_entry_point(input: &Mirrors) -> ((usize,usize), usize) {
+    count_illuminated_tiles_all_entries(input).into_iter()
+        .max_by_key(|(coords, count)| (*count, std::cmp::Reverse(*coords)))
+        .expect("Grid has no entry points")
+}
+
 #[aoc(day16, part1)]
 pub fn solve_part1(input: &Mirrors) -> usize {
-    let Mirrors {graph: gr, outer_max:om, inner_max:im} = input;
+    let Mirrors {graph: gr, optics: _, outer_max:om, inner_max:im} = input;
     let start_node = MirrorNode { coords: (1,0), beam_from: IncidenceDirection::Source };
     count_illuminated_tiles(&gr, *om, *im, start_node)
 }
 
 #[aoc(day16, part2)]
 pub fn solve_part2(input: &Mirrors) -> usize {
-    let Mirrors {graph: gr, outer_max:om, inner_max:im} = input;
+    best_entry_point(input).1
+}
+
+#[aoc(day16, part2, Bfs)]
+pub fn solve_part2_bfs(input: &Mirrors) -> usize {
+    let Mirrors {graph: gr, optics: _, outer_max:om, inner_max:im} = input;
     let top_sources = (1..*im).into_par_iter().map(|x| (0, x));
     let left_sources = (1..*om).into_par_iter().map(|x| (x,0));
     let right_sources = (1..*om).into_par_iter().map(|x| (x,*im));
@@ -295,4 +462,117 @@ r".|...\....
         let ans = solve_part2(&input);
         assert_eq!(ans, 51);
     }
+
+    #[test]
+    fn day16_solve_p2_bfs() {
+        let input = input_generator(TEST_INPUT);
+        let ans = solve_part2_bfs(&input);
+        assert_eq!(ans, 51);
+    }
+
+    #[test]
+    fn day16_all_entries_match_bfs() {
+        let input = input_generator(TEST_INPUT);
+        let Mirrors {graph: gr, optics: _, outer_max: om, inner_max: im} = &input;
+        let all_entries = count_illuminated_tiles_all_entries(&input);
+        assert_eq!(all_entries.len(), 40);
+        for (coords, count) in all_entries.iter() {
+            let start_node = MirrorNode { coords: *coords, beam_from: IncidenceDirection::Source };
+            assert_eq!(*count, count_illuminated_tiles(gr, *om, *im, start_node));
+        }
+        assert!(all_entries.contains(&((1,0), 46)));
+    }
+
+    #[test]
+    fn day16_best_entry_point() {
+        let input = input_generator(TEST_INPUT);
+        /* Beam enters from the top in the fourth column */
+        assert_eq!(best_entry_point(&input), ((0,4), 51));
+    }
+
+    #[test]
+    fn day16_render_energized() {
+        let input = input_generator(TEST_INPUT);
+        let trace = trace_beam(&input, (1,0));
+        assert_eq!(trace.count(), 46);
+        assert_eq!(trace.render_energized(),
+"######....
+.#...#....
+.#...#####
+.#...##...
+.#...##...
+.#...##...
+.#..####..
+########..
+.#######..
+.#...#.#..");
+    }
+
+    #[test]
+    fn day16_render_directions() {
+        let input = input_generator(TEST_INPUT);
+        let trace = trace_beam(&input, (1,0));
+        assert_eq!(trace.render_directions(&input),
+r">|<<<\....
+|v-.\^....
+.v...|->>>
+.v...v^.|.
+.v...v^...
+.v...v^..\
+.v../2\\..
+<->-/vv|..
+.|<<<2-|.\
+.v//.|.v..");
+        assert_eq!(trace.directions[(6,5)], BEAM_RIGHT | BEAM_DOWN);
+        assert!(trace.energized[(0,0)]);
+        assert!(!trace.energized[(9,9)]);
+    }
+
+    #[test]
+    fn day16_unknown_optic() {
+        let err = input_generator_with_table(".|.\n.x.", &OpticsTable::puzzle()).unwrap_err();
+        assert_eq!(err, MirrorsError::UnknownOptic(UnknownOpticError {line: 2, column: 2, ch: 'x'}));
+        assert_eq!(err.to_string(), "Unexpected char 'x' at line 2, column 2");
+        let err = input_generator_with_table("", &OpticsTable::puzzle()).unwrap_err();
+        assert_eq!(err, MirrorsError::EmptyGrid);
+    }
+
+    #[test]
+    fn day16_custom_optics() {
+        use IncidenceDirection::*;
+        let table = OpticsTable::puzzle()
+            /* absorbs every beam */
+            .with_element('#', &[]).unwrap()
+            /* one-way mirror: passes beams going right, sends beams going left up */
+            .with_element('>', &[
+                    (Left, &[Left]), (Right, &[Below]), (Above, &[Above]), (Below, &[Below])]).unwrap()
+            /* splits a beam going right into right, up and down */
+            .with_element('+', &[
+                    (Left, &[Left, Above, Below]), (Right, &[Right]), (Above, &[Above]), (Below, &[Below])]).unwrap();
+        let input = input_generator_with_table(
+"...#.
+.....
+..+..
+.....
+.....", &table).unwrap();
+        let trace = trace_beam(&input, (1,0));
+        assert_eq!(trace.render_energized(), "####.\n.....\n.....\n.....\n.....");
+        let trace = trace_beam(&input, (3,0));
+        assert_eq!(trace.render_energized(), "..#..\n..#..\n#####\n..#..\n..#..");
+        assert_eq!(trace.render_directions(&input), "..^#.\n..^..\n>>+>>\n..v..\n..v..");
+        let input = input_generator_with_table("..>..", &table).unwrap();
+        assert_eq!(trace_beam(&input, (1,0)).count(), 5);
+        assert_eq!(trace_beam(&input, (1,6)).render_energized(), "..###");
+    }
+
+    #[test]
+    fn day16_source_in_optic() {
+        use IncidenceDirection::*;
+        let err = OpticsTable::puzzle().with_element('s', &[(Source, &[Right])]).unwrap_err();
+        assert_eq!(err, SourceInOpticError {ch: 's'});
+        assert_eq!(err.to_string(), "Optic 's' uses Source as a side of its tile");
+        let err = OpticsTable::puzzle().with_element('s', &[(Left, &[Left, Source])]).unwrap_err();
+        assert_eq!(err, SourceInOpticError {ch: 's'});
+        assert!(OpticsTable::puzzle().get('.').unwrap().outgoing(Source).is_empty());
+    }
 }
@@ -1,8 +1,5 @@
-use petgraph::{graphmap::DiGraphMap, algo::dijkstra};
-
-#[allow(dead_code)]
 #[derive(Clone,Copy,Debug,Hash,PartialEq,Eq,PartialOrd,Ord)]
-enum Direction {
+pub enum Direction {
     Left,
     Right,
     Up,
@@ -12,78 +9,89 @@ enum Direction {
 
 #[derive(Clone,Copy,Debug,Hash,PartialEq,Eq,PartialOrd,Ord)]
 pub struct HeatlossNode {
-    coords: (usize,usize),
-    dir: Direction,
-    travelled: u32,
+    pub coords: (usize,usize),
+    pub dir: Direction,
+    pub travelled: u32,
 }
 
-pub fn input_generator_p1(input: &str) -> (DiGraphMap<HeatlossNode,u32>, (usize, usize)) {
-    let grid = input.lines()
+pub fn parse_heatloss_grid(input: &str) -> Vec<Vec<u32>> {
+    input.lines()
         .map(|line| {
             line.chars()
-                .map(|ch: char| ch.to_string().parse::<u32>().unwrap())
+                .map(|ch: char| ch.to_digit(10).expect("Expected a digit"))
                 .collect::<Vec<u32>>()
         })
-    .collect::<Vec<Vec<u32>>>();
-    let outer_max = grid.len();
-    let inner_max = grid[0].len();
-    let mut out = DiGraphMap::<HeatlossNode,u32>::with_capacity(16*input.len(), 3*16*input.len());
-    for i in 0..outer_max {
-        for j in 0..inner_max {
-            connect_edges_p1(&mut out, &grid, i, j);
-        }
-    }
-    (out, (outer_max - 1, inner_max - 1))
+    .collect::<Vec<Vec<u32>>>()
 }
 
-fn connect_edges_p1(graph: &mut DiGraphMap<HeatlossNode,u32>, grid: &Vec<Vec<u32>>, i: usize, j: usize) {
-    if (i == 0) && (j == 0) {
-        /* special start node */
-        let strt = HeatlossNode {coords: (0,0), dir: Direction::Start, travelled: 0};
-        let below_strt = HeatlossNode {coords: (1,0), dir: Direction::Down, travelled:  1};
-        let right_of_strt = HeatlossNode {coords: (0,1), dir: Direction::Right, travelled:  1};
-        graph.add_edge(strt, below_strt, grid[1][0]);
-        graph.add_edge(strt, right_of_strt, grid[0][1]);
-    } else {
-        /* all other nodes */
-        for direction in vec![Direction::Left, Direction::Right, Direction::Up, Direction::Down].iter() {
-            for dist_travelled in 1..=3 {
-                /* curent node */
-                let foo = HeatlossNode {coords: (i,j), dir: *direction, travelled: dist_travelled};
-                /* Don't go out of bounds && No 180° turns && Don't travel more than 3 tiles in one
-                 * direction*/
-                if (i < grid.len() - 1) && (*direction != Direction::Up) && passes_three_tile_check(&foo, Direction::Down){
-                    let new_travelled = calc_dist_travelled(direction, Direction::Down, dist_travelled);
-                    let bar = HeatlossNode {coords: (i + 1,j), dir: Direction::Down, travelled: new_travelled};
-                    graph.add_edge(foo, bar, grid[i + 1][j]);
-                }
-                if (i > 0) && (*direction != Direction::Down) && passes_three_tile_check(&foo, Direction::Up) {
-                    let new_travelled = calc_dist_travelled(direction, Direction::Up, dist_travelled);
-                    let bar = HeatlossNode {coords: (i - 1,j), dir: Direction::Up, travelled: new_travelled};
-                    graph.add_edge(foo, bar, grid[i - 1][j]);
-                }
-                if (j < grid[0].len() - 1) && (*direction != Direction::Left) && passes_three_tile_check(&foo, Direction::Right){
-                    let new_travelled = calc_dist_travelled(direction, Direction::Right, dist_travelled);
-                    let bar = HeatlossNode {coords: (i,j + 1), dir: Direction::Right, travelled: new_travelled};
-                    graph.add_edge(foo, bar, grid[i][j + 1]);
-                }
-                if (j > 0) && (*direction != Direction::Right) && passes_three_tile_check(&foo, Direction::Left) {
-                    let new_travelled = calc_dist_travelled(direction, Direction::Left, dist_travelled);
-                    let bar = HeatlossNode {coords: (i,j - 1), dir: Direction::Left, travelled: new_travelled};
-                    graph.add_edge(foo, bar, grid[i][j - 1]);
-                }
-            }
+impl Direction {
+    const MOVES: [Direction; 4] = [Direction::Left, Direction::Right, Direction::Up, Direction::Down];
+
+    #[inline]
+    fn opposite(&self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Start => Direction::Start,
         }
     }
 }
 
-/* Don't travel more than 3 tiles in one direction */
-#[inline]
-fn passes_three_tile_check(node: &HeatlossNode, direction: Direction) -> bool {
-    if (node.dir == direction) && (node.travelled == 3) {
-        return false;
+/* Dense state index of (coords, dir, travelled) for the implicit state space */
+struct CrucibleStates {
+    rows: usize,
+    cols: usize,
+    max_run: u32,
+}
+
+impl CrucibleStates {
+    fn len(&self) -> usize {
+        self.rows * self.cols * 5 * (self.max_run as usize + 1)
+    }
+
+    #[inline]
+    fn index(&self, node: &HeatlossNode) -> usize {
+        let (i, j) = node.coords;
+        ((i * self.cols + j) * 5 + node.dir as usize) * (self.max_run as usize + 1) + node.travelled as usize
+    }
+
+    #[inline]
+    fn node(&self, idx: usize) -> HeatlossNode {
+        let travelled = (idx % (self.max_run as usize + 1)) as u32;
+        let idx = idx / (self.max_run as usize + 1);
+        let dir = [Direction::Left, Direction::Right, Direction::Up, Direction::Down, Direction::Start][idx % 5];
+        let idx = idx / 5;
+        HeatlossNode {coords: (idx / self.cols, idx % self.cols), dir, travelled}
+    }
+
+    /* Must travel at least min_run tiles before turning and at most max_run tiles in one
+     * direction. No 180° turns and don't go out of bounds. */
+    fn successors(&self, node: &HeatlossNode, min_run: u32) -> impl Iterator<Item = HeatlossNode> + '_ {
+        let node = *node;
+        Direction::MOVES.into_iter()
+            .filter(move |next_dir| *next_dir != node.dir.opposite())
+            .filter(move |next_dir| {
+                if *next_dir == node.dir {
+                    node.travelled < self.max_run
+                } else {
+                    node.dir == Direction::Start || node.travelled >= min_run
+                }
+            })
+            .filter_map(move |next_dir| {
+                let (i, j) = node.coords;
+                let coords = match next_dir {
+                    Direction::Left if j > 0 => (i, j - 1),
+                    Direction::Right if j + 1 < self.cols => (i, j + 1),
+                    Direction::Up if i > 0 => (i - 1, j),
+                    Direction::Down if i + 1 < self.rows => (i + 1, j),
+                    _ => return None,
+                };
+                let travelled = calc_dist_travelled(&node.dir, next_dir, node.travelled);
+                Some(HeatlossNode {coords, dir: next_dir, travelled})
+            })
     }
-    true
 }
 
 /* Reset distance when changing directions */
@@ -95,99 +103,102 @@ fn calc_dist_travelled(curr_direction: &Direction, next_direction: Direction, di
     1
 }
 
-#[aoc(day17, part1)]
-pub fn solve_part1(input: &str) -> u32 {
-    let (graph, last_node) = input_generator_p1(input);
-    let strt = HeatlossNode {coords: (0,0), dir: Direction::Start, travelled: 0};
-    let ans = dijkstra(&graph, strt, None, |edge_ref| *edge_ref.2);
-    ans.into_iter()
-        .filter(|el| el.0.coords == last_node)
-        .map(|el| el.1)
-        .min().unwrap()
-}
-
-pub fn input_generator_p2(input: &str) -> (DiGraphMap<HeatlossNode,u32>, (usize, usize)) {
-    let grid = input.lines()
-        .map(|line| {
-            line.chars()
-                .map(|ch: char| ch.to_string().parse::<u32>().unwrap())
-                .collect::<Vec<u32>>()
-        })
-    .collect::<Vec<Vec<u32>>>();
-    let outer_max = grid.len();
-    let inner_max = grid[0].len();
-    let mut out = DiGraphMap::<HeatlossNode,u32>::with_capacity(40*input.len(), 18*input.len());
-    for i in 0..outer_max {
-        for j in 0..inner_max {
-            connect_edges_p2(&mut out, &grid, i, j);
-        }
+/* Dijkstra with a bucket queue (Dial's algorithm) over the implicit state space from the top
+ * left to the bottom right tile. Returns the minimal heat loss and the path taken, starting
+ * with the start node. Returns None if the end can't be reached. */
+pub fn find_min_heatloss_path(grid: &[Vec<u32>], min_run: u32, max_run: u32)
+    -> Option<(u32, Vec<HeatlossNode>)>
+{
+    let rows = grid.len();
+    let cols = grid.first().map_or(0, |row| row.len());
+    if rows == 0 || cols == 0 || max_run == 0 {
+        return None;
     }
-    (out, (outer_max - 1, inner_max - 1))
-}
-
-fn connect_edges_p2(graph: &mut DiGraphMap<HeatlossNode,u32>, grid: &Vec<Vec<u32>>, i: usize, j: usize) {
-    if (i == 0) && (j == 0) {
-        /* special start node */
-        let strt = HeatlossNode {coords: (0,0), dir: Direction::Start, travelled: 0};
-        let below_strt = HeatlossNode {coords: (1,0), dir: Direction::Down, travelled:  1};
-        let right_of_strt = HeatlossNode {coords: (0,1), dir: Direction::Right, travelled:  1};
-        graph.add_edge(strt, below_strt, grid[1][0]);
-        graph.add_edge(strt, right_of_strt, grid[0][1]);
-    } else {
-        /* all other nodes */
-        for direction in vec![Direction::Left, Direction::Right, Direction::Up, Direction::Down].iter() {
-            for dist_travelled in 1..=10 {
-                /* curent node */
-                let foo = HeatlossNode {coords: (i,j), dir: *direction, travelled: dist_travelled};
-                /* Don't go out of bounds && No 180° turns && Always travel between 4 and 10 tiles in one
-                 * direction*/
-                if (i < grid.len() - 1) && (*direction != Direction::Up) && p2_dist_check(&foo, Direction::Down){
-                    let new_travelled = calc_dist_travelled(direction, Direction::Down, dist_travelled);
-                    let bar = HeatlossNode {coords: (i + 1,j), dir: Direction::Down, travelled: new_travelled};
-                    graph.add_edge(foo, bar, grid[i + 1][j]);
-                }
-                if (i > 0) && (*direction != Direction::Down) && p2_dist_check(&foo, Direction::Up) {
-                    let new_travelled = calc_dist_travelled(direction, Direction::Up, dist_travelled);
-                    let bar = HeatlossNode {coords: (i - 1,j), dir: Direction::Up, travelled: new_travelled};
-                    graph.add_edge(foo, bar, grid[i - 1][j]);
-                }
-                if (j < grid[0].len() - 1) && (*direction != Direction::Left) && p2_dist_check(&foo, Direction::Right){
-                    let new_travelled = calc_dist_travelled(direction, Direction::Right, dist_travelled);
-                    let bar = HeatlossNode {coords: (i,j + 1), dir: Direction::Right, travelled: new_travelled};
-                    graph.add_edge(foo, bar, grid[i][j + 1]);
+    let states = CrucibleStates {rows, cols, max_run};
+    let end = (rows - 1, cols - 1);
+    let num_buckets = grid.iter().flatten().copied().max().unwrap_or(0) as usize + 1;
+    let mut dist = vec![u32::MAX; states.len()];
+    let mut prev = vec![usize::MAX; states.len()];
+    let mut buckets = vec![Vec::<usize>::new(); num_buckets];
+    let strt = HeatlossNode {coords: (0,0), dir: Direction::Start, travelled: 0};
+    let strt_idx = states.index(&strt);
+    dist[strt_idx] = 0;
+    buckets[0].push(strt_idx);
+    let mut queued = 1usize;
+    let mut cost = 0u32;
+    while queued > 0 {
+        while let Some(idx) = buckets[cost as usize % num_buckets].pop() {
+            queued -= 1;
+            if dist[idx] != cost {
+                continue;// stale entry
+            }
+            let node = states.node(idx);
+            if node.coords == end && (node.travelled >= min_run || node.dir == Direction::Start) {
+                let mut path = vec![node];
+                let mut curr = idx;
+                while prev[curr] != usize::MAX {
+                    curr = prev[curr];
+                    path.push(states.node(curr));
                 }
-                if (j > 0) && (*direction != Direction::Right) && p2_dist_check(&foo, Direction::Left) {
-                    let new_travelled = calc_dist_travelled(direction, Direction::Left, dist_travelled);
-                    let bar = HeatlossNode {coords: (i,j - 1), dir: Direction::Left, travelled: new_travelled};
-                    graph.add_edge(foo, bar, grid[i][j - 1]);
+                path.reverse();
+                return Some((cost, path));
+            }
+            for next in states.successors(&node, min_run) {
+                let next_idx = states.index(&next);
+                let next_cost = cost + grid[next.coords.0][next.coords.1];
+                if next_cost < dist[next_idx] {
+                    dist[next_idx] = next_cost;
+                    prev[next_idx] = idx;
+                    buckets[next_cost as usize % num_buckets].push(next_idx);
+                    queued += 1;
                 }
             }
         }
+        cost += 1;
     }
+    None
 }
 
-/* Travel between 4 and 10 tiles */
-#[inline]
-fn p2_dist_check(node: &HeatlossNode, direction: Direction) -> bool {
-    if (node.dir != direction) && (node.travelled < 4) {
-        return false;
-    }
-    if (node.dir == direction) && (node.travelled == 10) {
-        return false;
+/* Draws the path over the digit grid with the puzzle's >, <, ^, v arrows. The start tile
+ * keeps its digit. */
+pub fn render_heatloss_path(grid: &[Vec<u32>], path: &[HeatlossNode]) -> String {
+    let mut canvas = grid.iter()
+        .map(|row| {
+            row.iter()
+                .map(|digit| char::from_digit(*digit, 10).unwrap())
+                .collect::<Vec<char>>()
+        })
+    .collect::<Vec<Vec<char>>>();
+    for node in path.iter() {
+        let arrow = match node.dir {
+            Direction::Left => '<',
+            Direction::Right => '>',
+            Direction::Up => '^',
+            Direction::Down => 'v',
+            Direction::Start => continue,
+        };
+        canvas[node.coords.0][node.coords.1] = arrow;
     }
-    true
+    canvas.into_iter()
+        .map(|row| row.into_iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[aoc(day17, part1)]
+pub fn solve_part1(input: &str) -> u32 {
+    let grid = parse_heatloss_grid(input);
+    find_min_heatloss_path(&grid, 0, 3)
+        .expect("End is unreachable")
+        .0
 }
 
 #[aoc(day17, part2)]
 pub fn solve_part2(input: &str) -> u32 {
-    let input = input_generator_p2(input);
-    let (input, last_node) = input;
-    let strt = HeatlossNode {coords: (0,0), dir: Direction::Start, travelled: 0};
-    let ans = dijkstra(&input, strt, None, |edge_ref| *edge_ref.2);
-    ans.into_iter()
-        .filter(|el| el.0.coords == last_node)
-        .map(|el| el.1)
-        .min().expect("End is unreachable")
+    let grid = parse_heatloss_grid(input);
+    find_min_heatloss_path(&grid, 4, 10)
+        .expect("End is unreachable")
+        .0
 }
 
 #[cfg(test)]
@@ -264,4 +275,88 @@ mod tests {
         let ans = solve_part2(TEST_INPUT);
         assert_eq!(ans, 94);
     }
+
+    #[test]
+    fn day17_p2_2() {
+        const INPUT: &str =
+"111111111111
+999999999991
+999999999991
+999999999991
+999999999991";
+        let ans = solve_part2(INPUT);
+        assert_eq!(ans, 71);
+    }
+
+    #[test]
+    fn day17_path_obeys_run_lengths() {
+        let grid = parse_heatloss_grid(TEST_INPUT);
+        for (min_run, max_run, expected) in [(0, 3, 102), (4, 10, 94)] {
+            let (heat_loss, path) = find_min_heatloss_path(&grid, min_run, max_run).unwrap();
+            assert_eq!(heat_loss, expected);
+            assert_eq!(path.first().unwrap().coords, (0,0));
+            assert_eq!(path.last().unwrap().coords, (12,12));
+            assert!(path.last().unwrap().travelled >= min_run);
+            let path_loss = path.iter()
+                .skip(1)
+                .map(|node| grid[node.coords.0][node.coords.1])
+                .sum::<u32>();
+            assert_eq!(path_loss, heat_loss);
+            for pair in path.windows(2) {
+                assert!(pair[1].travelled <= max_run);
+                if pair[0].dir != pair[1].dir && pair[0].dir != Direction::Start {
+                    assert!(pair[0].travelled >= min_run);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn day17_unreachable() {
+        let grid = parse_heatloss_grid("111\n111");
+        assert_eq!(find_min_heatloss_path(&grid, 4, 10), None);
+        assert_eq!(find_min_heatloss_path(&grid, 0, 3).unwrap().0, 3);
+    }
+
+    #[test]
+    fn day17_render_path_p1() {
+        let grid = parse_heatloss_grid(TEST_INPUT);
+        let (_, path) = find_min_heatloss_path(&grid, 0, 3).unwrap();
+        let rendered = render_heatloss_path(&grid, &path);
+        assert_eq!(rendered,
+"2>>34^>>>1323
+32v>>>35v5623
+32552456v>>54
+3446585845v52
+4546657867v>6
+14385987984v4
+44578769877v6
+36378779796v>
+465496798688v
+456467998645v
+12246868655<v
+25465488877v5
+43226746555v>");
+    }
+
+    #[test]
+    fn day17_render_path_p2() {
+        let grid = parse_heatloss_grid(TEST_INPUT);
+        let (_, path) = find_min_heatloss_path(&grid, 4, 10).unwrap();
+        let rendered = render_heatloss_path(&grid, &path);
+        assert_eq!(rendered,
+"2>>>>>>>>1323
+32154535v5623
+32552456v4254
+34465858v5452
+45466578v>>>>
+143859879845v
+445787698776v
+363787797965v
+465496798688v
+456467998645v
+122468686556v
+254654888773v
+432267465553v");
+    }
 }
@@ -1,58 +1,111 @@
+use std::collections::BTreeMap;
 use nom::{
     IResult,
     Parser,
-    branch::alt,
     bytes::complete::{tag, take_while1},
-    combinator::{all_consuming, value},
+    combinator::all_consuming,
     sequence::separated_pair,
     multi::{separated_list0,separated_list1},
     };
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
-pub struct CubeColors {
-    red: u32,
-    green: u32,
-    blue: u32,
+/* Cube counts by colour name, colours that aren't in the bag count as 0 */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bag {
+    cubes: BTreeMap<String, u32>,
 }
 
-impl CubeColors {
-    fn set_color(&mut self, value: u32, color: Color) {
-        *match color {
-            Color::Red => &mut self.red,
-            Color::Green => &mut self.green,
-            Color::Blue => &mut self.blue,
-        } = value;
+impl Bag {
+    pub fn new(cubes: &[(&str, u32)]) -> Self {
+        cubes.iter()
+            .map(|(color, count)| (*count, color.to_string()))
+            .collect()
+    }
+
+    pub fn get(&self, color: &str) -> u32 {
+        self.cubes.get(color).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, color: &str, count: u32) {
+        if count == 0 {
+            self.cubes.remove(color);
+        } else {
+            self.cubes.insert(color.to_string(), count);
+        }
+    }
+
+    pub fn colors(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.cubes.iter().map(|(color, count)| (color.as_str(), *count))
+    }
+
+    /* Every draw from `other` could have come out of this bag */
+    pub fn holds(&self, other: &Bag) -> bool {
+        other.colors().all(|(color, count)| count <= self.get(color))
+    }
+
+    /* Smallest bag holding both */
+    pub fn max(&self, other: &Bag) -> Bag {
+        let mut out = self.clone();
+        for (color, count) in other.colors() {
+            if count > out.get(color) {
+                out.set(color, count);
+            }
+        }
+        out
+    }
+
+    /* Product of the counts of the given colours */
+    pub fn power(&self, colors: &[&str]) -> u32 {
+        colors.iter().map(|color| self.get(color)).product()
     }
 }
 
-impl FromIterator<(u32,Color)> for CubeColors {
-    fn from_iter<T: IntoIterator<Item = (u32, Color)>>(iter: T) -> Self {
-        let mut cube_colors = Self::default();
+/* A colour given twice keeps its last count */
+impl FromIterator<(u32,String)> for Bag {
+    fn from_iter<T: IntoIterator<Item = (u32, String)>>(iter: T) -> Self {
+        let mut bag = Self::default();
         for (count, color) in iter {
-            cube_colors.set_color(count, color);
+            bag.set(&color, count);
         }
-        cube_colors
+        bag
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Color {
-    Red,
-    Green,
-    Blue,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub id: u32,
+    pub draws: Vec<Bag>,
+}
+
+impl Game {
+    pub fn is_possible_with(&self, bag: &Bag) -> bool {
+        self.draws.iter().all(|draw| bag.holds(draw))
+    }
+
+    pub fn min_bag(&self) -> Bag {
+        self.draws.iter()
+            .fold(Bag::default(), |bag, draw| bag.max(draw))
+    }
 }
 
+/* Smallest bag that makes every one of the games possible */
+pub fn min_bag_for_games(games: &[Game]) -> Bag {
+    games.iter()
+        .fold(Bag::default(), |bag, game| bag.max(&game.min_bag()))
+}
+
+const PUZZLE_COLORS: [&str; 3] = ["red", "green", "blue"];
+
 #[aoc_generator(day2)]
-pub fn input_generator(input: &str) -> Vec<Vec<CubeColors>> {
+pub fn input_generator(input: &str) -> Vec<Game> {
     match all_consuming(separated_list0(tag("\n"), parse_game))(input) {
         Ok((_,v)) => v,
         Err(e) => panic!("{}",e),
     }
 }
 
-fn parse_game(input: &str) -> IResult<&str, Vec<CubeColors>> {
+fn parse_game(input: &str) -> IResult<&str, Game> {
     separated_pair(parse_game_id, tag(": "), parse_game_results)
-        .map(|(_, v)| v)
+        .map(|(id, draws)| Game {id, draws})
         .parse(input)
 }
 
@@ -66,25 +119,21 @@ fn parse_game_id(input: &str) -> IResult<&str, u32> {
     Ok((out, num))
 }
 
-fn parse_game_results(input: &str) -> IResult<&str, Vec<CubeColors>> {
+fn parse_game_results(input: &str) -> IResult<&str, Vec<Bag>> {
     separated_list0(tag("; "), parse_game_subset)(input)
 }
 
-fn parse_game_subset(input: &str) -> IResult<&str, CubeColors> {
+fn parse_game_subset(input: &str) -> IResult<&str, Bag> {
     separated_list1(tag(", "), parse_color_val)
-        .map(|el| el.into_iter().collect())
+        .map(|el| el.into_iter().map(|(count, color)| (count, color.to_string())).collect())
         .parse(input)
 }
-fn parse_color_val(input: &str) -> IResult<&str, (u32,Color)> {
+fn parse_color_val(input: &str) -> IResult<&str, (u32,&str)> {
     separated_pair(parse_val, tag(" "), parse_color)(input)
 }
 
-fn parse_color(input: &str) -> IResult<&str, Color> {
-    alt((
-            value(Color::Red, tag("red")),
-            value(Color::Green, tag("green")),
-            value(Color::Blue, tag("blue")),
-    ))(input)
+fn parse_color(input: &str) -> IResult<&str, &str> {
+    take_while1(char::is_alphabetic)(input)
 }
 
 fn parse_val(input: &str) -> IResult<&str, u32> {
@@ -93,39 +142,19 @@ fn parse_val(input: &str) -> IResult<&str, u32> {
     Ok((i, num))
 }
 
-fn does_game_pass_part1(game: &[CubeColors]) -> bool {
-    !game.iter()
-        .any(|el| {
-            (el.red > 12)
-                || (el.green > 13)
-                || (el.blue > 14)
-        })
-}
-
 #[aoc(day2, part1)]
-pub fn solve_part1(input: &Vec<Vec<CubeColors>>) -> u32 {
+pub fn solve_part1(input: &[Game]) -> u32 {
+    let bag = Bag::new(&[("red", 12), ("green", 13), ("blue", 14)]);
     input.iter()
-        .enumerate()
-        .filter(|(_,x)| does_game_pass_part1(x))
-        .map(|(n,_)| n as u32 + 1)
+        .filter(|game| game.is_possible_with(&bag))
+        .map(|game| game.id)
         .sum()
 }
 
-fn min_num_cubes_for_part2(game: &[CubeColors]) -> (u32,u32,u32) {
-    game.iter()
-        .fold((0,0,0), |(r,g,b), el| {
-            let r_new = if el.red > r {el.red} else {r};
-            let g_new = if el.green > g {el.green} else {g};
-            let b_new = if el.blue > b {el.blue} else {b};
-            (r_new, g_new, b_new)
-        })
-}
-
 #[aoc(day2, part2)]
-pub fn solve_part2(input: &Vec<Vec<CubeColors>>) -> u32 {
+pub fn solve_part2(input: &[Game]) -> u32 {
     input.iter()
-        .map(|x| min_num_cubes_for_part2(x))
-        .map(|(r,g,b)| r * g * b)
+        .map(|game| game.min_bag().power(&PUZZLE_COLORS))
         .sum()
 }
 
@@ -137,7 +166,7 @@ mod tests {
     fn test_parse_color() {
         const INPUT: &str = "blue";
         let ans = parse_color(INPUT);
-        assert_eq!(ans, Ok(("", Color::Blue)))
+        assert_eq!(ans, Ok(("", "blue")))
     }
 
     #[test]
@@ -151,17 +180,20 @@ mod tests {
     fn test_parse_color_val() {
         const INPUT1: &str = "3 blue";
         let ans1 = parse_color_val(INPUT1);
-        assert_eq!(ans1, Ok(("", (3, Color::Blue))));
+        assert_eq!(ans1, Ok(("", (3, "blue"))));
     }
 
     #[test]
     fn test_parse_game_subset() {
         const INPUT1: &str = "4 red";
         let ans = parse_game_subset(INPUT1);
-        assert_eq!(ans, Ok(("", CubeColors{red: 4, green: 0, blue: 0})));
+        assert_eq!(ans, Ok(("", Bag::new(&[("red", 4), ("green", 0), ("blue", 0)]))));
         const INPUT2: &str = "3 blue, 1 green, 4 red";
         let ans = parse_game_subset(INPUT2);
-        assert_eq!(ans, Ok(("", CubeColors{red: 4, green: 1, blue: 3})))
+        assert_eq!(ans, Ok(("", Bag::new(&[("red", 4), ("green", 1), ("blue", 3)]))));
+        const INPUT3: &str = "3 blue, 1 green, 2 blue";
+        let ans = parse_game_subset(INPUT3);
+        assert_eq!(ans, Ok(("", Bag::new(&[("green", 1), ("blue", 2)]))))
     }
 
     #[test]
@@ -169,11 +201,11 @@ mod tests {
         const INPUT: &str = "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue";
         let ans = parse_game(INPUT);
         assert_eq!(ans, Ok(("",
-                    vec![
-                    CubeColors {red: 0, green: 2, blue: 1},
-                    CubeColors {red: 1, green: 3, blue: 4},
-                    CubeColors {red: 0, green: 1, blue: 1},
-                    ]
+                    Game {id: 2, draws: vec![
+                    Bag::new(&[("green", 2), ("blue", 1)]),
+                    Bag::new(&[("red", 1), ("green", 3), ("blue", 4)]),
+                    Bag::new(&[("green", 1), ("blue", 1)]),
+                    ]}
                     )))
     }
 
@@ -187,48 +219,31 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
     fn test_input_generator() {
         let ans = input_generator(TEST_INPUT1);
         assert_eq!(ans.len(), 5);
-        assert_eq!(ans[0].len(), 3);
-        assert_eq!(ans[0][0], CubeColors{red: 4, green: 0, blue: 3});
-        assert_eq!(ans[0][1], CubeColors{red: 1, green: 2, blue: 6});
-        assert_eq!(ans[0][2], CubeColors{red: 0, green: 2, blue: 0});
-        assert_eq!(ans[1].len(), 3);
-        assert_eq!(ans[2].len(), 3);
-        assert_eq!(ans[3].len(), 3);
-        assert_eq!(ans[4].len(), 2);
+        assert_eq!(ans[0].draws.len(), 3);
+        assert_eq!(ans[0].draws[0], Bag::new(&[("red", 4), ("blue", 3)]));
+        assert_eq!(ans[0].draws[1], Bag::new(&[("red", 1), ("green", 2), ("blue", 6)]));
+        assert_eq!(ans[0].draws[2], Bag::new(&[("green", 2)]));
+        assert_eq!(ans[1].draws.len(), 3);
+        assert_eq!(ans[2].draws.len(), 3);
+        assert_eq!(ans[3].draws.len(), 3);
+        assert_eq!(ans[4].draws.len(), 2);
+        assert_eq!(ans.iter().map(|game| game.id).collect::<Vec<u32>>(), vec![1, 2, 3, 4, 5]);
     }
 
     #[test]
     fn test_does_game_pass_part1() {
-        let test_game1: Vec<CubeColors> = vec![CubeColors{red: 1, green: 1, blue: 1}];
-        assert_eq!(does_game_pass_part1(&test_game1), true);
-        let test_game2: Vec<CubeColors> = vec![CubeColors{red: 13, green: 1, blue: 1}];
-        assert_eq!(does_game_pass_part1(&test_game2), false);
-        let test_game3: Vec<CubeColors> = vec![
-            CubeColors{red: 20, green: 8, blue: 6},
-            CubeColors{red: 4, green: 13, blue: 5},
-            CubeColors{red: 1, green: 5, blue: 0},
-        ];
-        assert_eq!(does_game_pass_part1(&test_game3), false);
-        let test_game4: Vec<CubeColors> = vec![CubeColors{red: 20, green: 20, blue: 20}];
-        assert_eq!(does_game_pass_part1(&test_game4), false);
-        let test_game5: Vec<CubeColors> = vec![CubeColors{red: 12, green: 13, blue: 14}];
-        assert_eq!(does_game_pass_part1(&test_game5), true);
-    }
-
-    #[test]
-    fn test_cube_colors_comparison() {
-        const EXAMPLE_CUBE_COLORS: CubeColors = CubeColors{red: 1, green: 1, blue: 1};
-        /* Should be only greater than */
-        assert_eq!(EXAMPLE_CUBE_COLORS < CubeColors{red: 2, green: 1, blue: 1}, true);
-        assert_eq!(EXAMPLE_CUBE_COLORS > CubeColors{red: 2, green: 1, blue: 1}, false);
-        /* Should be only less than */
-        assert_eq!(EXAMPLE_CUBE_COLORS > CubeColors{red: 0,  green: 1, blue: 1}, true);
-        assert_eq!(EXAMPLE_CUBE_COLORS < CubeColors{red: 0,  green: 1, blue: 1}, false);
-        /* blah */
-        assert_eq!(EXAMPLE_CUBE_COLORS < CubeColors{red: 0, green: 2, blue: 1}, false);
-        assert_eq!(EXAMPLE_CUBE_COLORS < CubeColors{red: 2, green: 0, blue: 1}, true);
-        assert_eq!(EXAMPLE_CUBE_COLORS > CubeColors{red: 0, green: 2, blue: 1}, true);
-        assert_eq!(EXAMPLE_CUBE_COLORS > CubeColors{red: 2, green: 0, blue: 1}, false);
+        let bag = Bag::new(&[("red", 12), ("green", 13), ("blue", 14)]);
+        let game = |draws: &[&[(&str, u32)]]| Game {id: 1, draws: draws.iter().map(|draw| Bag::new(draw)).collect()};
+        assert!(game(&[&[("red", 1), ("green", 1), ("blue", 1)]]).is_possible_with(&bag));
+        assert!(!game(&[&[("red", 13), ("green", 1), ("blue", 1)]]).is_possible_with(&bag));
+        assert!(!game(&[
+            &[("red", 20), ("green", 8), ("blue", 6)],
+            &[("red", 4), ("green", 13), ("blue", 5)],
+            &[("red", 1), ("green", 5)],
+        ]).is_possible_with(&bag));
+        assert!(!game(&[&[("red", 20), ("green", 20), ("blue", 20)]]).is_possible_with(&bag));
+        assert!(game(&[&[("red", 12), ("green", 13), ("blue", 14)]]).is_possible_with(&bag));
+        assert!(!game(&[&[("purple", 1)]]).is_possible_with(&bag));
     }
 
     #[test]
@@ -241,8 +256,30 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
     #[test]
     fn test_min_number_cubes_for_part2() {
         let input = input_generator(TEST_INPUT1);
-        let ans = min_num_cubes_for_part2(&input[0]);
-        assert_eq!(ans,(4,2,6));
+        let ans = input[0].min_bag();
+        assert_eq!((ans.get("red"), ans.get("green"), ans.get("blue")), (4,2,6));
+    }
+
+    #[test]
+    fn test_min_bag() {
+        let input = input_generator(TEST_INPUT1);
+        let ans = input[0].min_bag();
+        assert_eq!(ans, Bag::new(&[("red", 4), ("green", 2), ("blue", 6)]));
+        assert_eq!(min_bag_for_games(&input[..2]), Bag::new(&[("red", 4), ("green", 3), ("blue", 6)]));
+        let all = min_bag_for_games(&input);
+        assert_eq!(all, Bag::new(&[("red", 20), ("green", 13), ("blue", 15)]));
+        assert!(input.iter().all(|game| game.is_possible_with(&all)));
+    }
+
+    #[test]
+    fn test_arbitrary_colors() {
+        let input = input_generator("Game 7: 2 teal, 1 magenta; 3 teal\nGame 9: 4 magenta, 1 teal, 5 magenta");
+        assert_eq!(input[1].id, 9);
+        assert_eq!(input[1].draws[0], Bag::new(&[("teal", 1), ("magenta", 5)]));
+        let bag = Bag::new(&[("teal", 3), ("magenta", 1)]);
+        assert!(input[0].is_possible_with(&bag));
+        assert!(!input[1].is_possible_with(&bag));
+        assert_eq!(min_bag_for_games(&input).power(&["teal", "magenta"]), 15);
     }
 
     #[test]
@@ -252,4 +289,3 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
         assert_eq!(ans, 2286);
     }
 }
-
@@ -1,5 +1,7 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use itertools::Itertools;
+use ndarray::Array2;
 use nom::{
     Parser,
     IResult,
@@ -30,6 +32,14 @@ pub fn input_generator(input: &str) -> FallenBricks {
     FallenBricks {brick_locations: piled_bricks, adjacent_bricks: adjacent}
 }
 
+#[aoc_generator(day22, part1, HeightMap)]
+#[aoc_generator(day22, part2, HeightMap)]
+pub fn input_generator_height_map(input: &str) -> FallenBricks {
+    let falling_bricks = file_to_brick_vec(input);
+    let (piled_bricks, adjacent) = drop_and_stack_all_bricks_height_map(falling_bricks);
+    FallenBricks {brick_locations: piled_bricks, adjacent_bricks: adjacent}
+}
+
 fn file_to_brick_vec(input: &str) -> Vec<(usize, (u32,u32,u32),(u32,u32,u32))> {
     let mut bricks_vec = match all_consuming(separated_list1(tag("\n"), parse_brick_line))
         .parse(input) {
@@ -92,6 +102,44 @@ fn drop_and_stack_all_bricks(
     pile_of_bricks
 }
 
+type NumberedBrick = (usize,(u32,u32,u32),(u32,u32,u32));//(brick_id, p1, p2)
+
+/* Expects bricks to be sorted in ascending z1 order.
+ * Keeps the height and the topmost brick_id for every (x,y) column, so each brick
+ * drops in one step and its supporters are the top bricks at the resting height. */
+fn drop_and_stack_all_bricks_height_map(falling_bricks: Vec<NumberedBrick>)
+    -> (Vec<NumberedBrick>, Vec<BrickAdjNode>)
+{
+    let (x_max, y_max) = falling_bricks.iter()
+        .fold((0,0), |(xm,ym),(_,_,p2)| (xm.max(p2.0), ym.max(p2.1)));
+    let shape = (x_max as usize + 1, y_max as usize + 1);
+    let mut height = Array2::<u32>::zeros(shape);
+    let mut top_brick = Array2::<Option<usize>>::from_elem(shape, None);
+    let mut adjacent = vec![BrickAdjNode::default(); falling_bricks.len()];
+    let mut pile_of_bricks = Vec::<NumberedBrick>::new();
+    for (brick_id, mut p1, mut p2) in falling_bricks.into_iter() {
+        let footprint = (p1.0 as usize..=p2.0 as usize)
+            .cartesian_product(p1.1 as usize..=p2.1 as usize);
+        let rest_height = footprint.clone()
+            .map(|xy| height[xy])
+            .max()
+            .unwrap_or(0);
+        let drop = p1.2 - (rest_height + 1);
+        p1.2 -= drop;
+        p2.2 -= drop;
+        for xy in footprint {
+            if let (true, Some(id_below)) = (height[xy] == rest_height, top_brick[xy]) {
+                adjacent[id_below].above_set.insert(brick_id);
+                adjacent[brick_id].below_set.insert(id_below);
+            }
+            height[xy] = p2.2;
+            top_brick[xy] = Some(brick_id);
+        }
+        pile_of_bricks.push((brick_id, p1, p2))
+    }
+    (pile_of_bricks, adjacent)
+}
+
 #[inline]
 fn calc_brick_adjacency(
     bricks_vec: &[(usize,(u32,u32,u32),(u32,u32,u32))],
@@ -121,6 +169,7 @@ fn can_be_disintegrated_p1(adjacent_bricks: &[BrickAdjNode], brick_id: usize) ->
 }
 
 #[aoc(day22,part1)]
+#[aoc(day22,part1,HeightMap)]
 pub fn solve_day22_p1(input: &FallenBricks) -> usize {
     let FallenBricks {brick_locations: bricks, adjacent_bricks: adjacent} = input;
     bricks.into_iter()
@@ -141,6 +190,7 @@ fn disintegration_cascade_p2(adjacent_bricks: &[BrickAdjNode], falling_bricks: &
 }
 
 #[aoc(day22,part2)]
+#[aoc(day22,part2,HeightMap)]
 pub fn solve_day22_p2(input: &FallenBricks) -> usize {
     let FallenBricks {brick_locations: bricks, adjacent_bricks: adjacent} = input;
     let mut falling = HashSet::<usize>::new();
@@ -155,9 +205,150 @@ pub fn solve_day22_p2(input: &FallenBricks) -> usize {
         .sum::<usize>()
 }
 
+/* Both end points of a brick: (x1,y1,z1),(x2,y2,z2) */
+pub type BrickEnds = ((u32,u32,u32),(u32,u32,u32));
+
+/* Settled bricks that can be changed after the initial drop */
+#[derive(Clone,Debug,Default)]
+pub struct BrickPile {
+    bricks: Vec<Option<BrickEnds>>,//indexed by brick_id, None if removed
+    space: HashMap<(u32,u32,u32),usize>,
+}
+
+impl BrickPile {
+    pub fn new(input: &str) -> Self {
+        let falling_bricks = file_to_brick_vec(input);
+        let mut space = HashMap::<(u32,u32,u32), usize>::new();
+        let piled_bricks = drop_and_stack_all_bricks(falling_bricks, &mut space);
+        let mut bricks = vec![None; piled_bricks.len()];
+        for (brick_id, p1, p2) in piled_bricks.into_iter() {
+            bricks[brick_id] = Some((p1,p2));
+        }
+        BrickPile { bricks, space }
+    }
+
+    pub fn brick(&self, brick_id: usize) -> Option<BrickEnds> {
+        self.bricks.get(brick_id).copied().flatten()
+    }
+
+    /* Iterates over (brick_id, p1, p2) of all bricks still in the pile */
+    pub fn bricks(&self) -> impl Iterator<Item = (usize,(u32,u32,u32),(u32,u32,u32))> + '_ {
+        self.bricks.iter()
+            .enumerate()
+            .filter_map(|(id, b)| b.map(|(p1,p2)| (id,p1,p2)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.bricks.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /* Drops a new brick from the given position and returns its brick_id, the ends may be
+     * given in any order on every axis. Returns None if the brick would overlap a brick
+     * already in the pile. */
+    pub fn add_brick(&mut self, p1: (u32,u32,u32), p2: (u32,u32,u32)) -> Option<usize> {
+        let (p1, p2) = ((p1.0.min(p2.0), p1.1.min(p2.1), p1.2.min(p2.2)),
+            (p1.0.max(p2.0), p1.1.max(p2.1), p1.2.max(p2.2)));
+        if p1.2 == 0 || Self::cells(p1, p2).any(|pos| self.space.contains_key(&pos)) {
+            return None;
+        }
+        let brick_id = self.bricks.len();
+        self.bricks.push(None);
+        self.settle_brick(brick_id, p1, p2);
+        Some(brick_id)
+    }
+
+    /* Removes bricks and re-settles only the bricks resting (transitively) on them.
+     * Returns the ids of all bricks that moved, in the order they were dropped. */
+    pub fn remove_bricks(&mut self, brick_ids: &[usize]) -> Vec<usize> {
+        let mut to_resettle = HashSet::<usize>::new();
+        let mut stack = Vec::<usize>::new();
+        for &brick_id in brick_ids.iter() {
+            if self.brick(brick_id).is_none() {
+                continue;
+            }
+            stack.extend(self.bricks_above(brick_id));
+        }
+        while let Some(bid) = stack.pop() {
+            if brick_ids.contains(&bid) || !to_resettle.insert(bid) {
+                continue;
+            }
+            stack.extend(self.bricks_above(bid));
+        }
+        for &brick_id in brick_ids.iter() {
+            if let Some((p1,p2)) = self.bricks.get_mut(brick_id).and_then(Option::take) {
+                Self::cells(p1, p2).for_each(|pos| { self.space.remove(&pos); });
+            }
+        }
+        let mut to_resettle = to_resettle.into_iter()
+            .map(|bid| (bid, self.bricks[bid].unwrap()))
+            .collect::<Vec<_>>();
+        to_resettle.sort_unstable_by_key(|(bid,(p1,_))| (p1.2, *bid));//lowest brick first
+        let mut moved = Vec::<usize>::new();
+        for (bid, (p1,p2)) in to_resettle.into_iter() {
+            Self::cells(p1, p2).for_each(|pos| { self.space.remove(&pos); });
+            self.settle_brick(bid, p1, p2);
+            if self.bricks[bid] != Some((p1,p2)) {
+                moved.push(bid);
+            }
+        }
+        moved
+    }
+
+    /* All bricks that brick_id rests on */
+    pub fn bricks_below(&self, brick_id: usize) -> HashSet<usize> {
+        match self.brick(brick_id) {
+            Some(((x1,y1,z1),(x2,y2,_))) if z1 > 1 => (x1..=x2).cartesian_product(y1..=y2)
+                .filter_map(|(x,y)| self.space.get(&(x,y,z1-1)).copied())
+                .collect(),
+            _ => HashSet::new(),
+        }
+    }
+
+    /* All bricks resting on brick_id */
+    pub fn bricks_above(&self, brick_id: usize) -> HashSet<usize> {
+        match self.brick(brick_id) {
+            Some(((x1,y1,_),(x2,y2,z2))) => (x1..=x2).cartesian_product(y1..=y2)
+                .filter_map(|(x,y)| self.space.get(&(x,y,z2+1)).copied())
+                .collect(),
+            None => HashSet::new(),
+        }
+    }
+
+    fn settle_brick(&mut self, brick_id: usize, mut p1: (u32,u32,u32), mut p2: (u32,u32,u32)) {
+        while p1.2 > 1 && (p1.0..=p2.0).cartesian_product(p1.1..=p2.1)
+            .all(|(x,y)| !self.space.contains_key(&(x,y,p1.2-1))) {
+                p1.2 -= 1;// z1--
+                p2.2 -= 1;// z2--
+        }
+        self.space.extend(Self::cells(p1, p2).map(|pos| (pos, brick_id)));
+        self.bricks[brick_id] = Some((p1,p2));
+    }
+
+    fn cells(p1: (u32,u32,u32), p2: (u32,u32,u32)) -> impl Iterator<Item = (u32,u32,u32)> {
+        (p1.0..=p2.0).cartesian_product(p1.1..=p2.1)
+            .cartesian_product(p1.2..=p2.2)
+            .map(|((x,y),z)| (x,y,z))
+    }
+}
+
+/* Exports the settled pile in the puzzle's "x,y,z~x,y,z" format */
+impl fmt::Display for BrickPile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines = self.bricks()
+            .map(|(_,(x1,y1,z1),(x2,y2,z2))| format!("{},{},{}~{},{},{}", x1, y1, z1, x2, y2, z2))
+            .join("\n");
+        write!(f, "{}", lines)
+    }
+}
+
 #[cfg(test)]
 mod test{
     use super::*;
+    use crate::test_util::TestRng;
     const TEST_INPUT: &str =
 "1,0,1~1,2,1
 0,0,2~2,0,2
@@ -205,4 +396,159 @@ mod test{
         let ans = solve_day22_p2(&input);
         assert_eq!(ans, 7);
     }
+
+    fn sorted_brick_coords(pile: &BrickPile) -> Vec<BrickEnds> {
+        pile.bricks()
+            .map(|(_,p1,p2)| (p1,p2))
+            .sorted()
+            .collect()
+    }
+
+    /* Deterministic pseudo-random bricks so the pile is larger than the example */
+    fn generate_falling_bricks(num_bricks: usize, seed: u64) -> String {
+        generate_bricks_below(num_bricks, 200, seed)
+    }
+
+    /* Bricks starting below a height that grows with their number, so large piles stay tall
+     * and tightly packed like the puzzle input */
+    fn generate_dense_falling_bricks(num_bricks: usize, seed: u64) -> String {
+        generate_bricks_below(num_bricks, num_bricks as u32 / 3 + 10, seed)
+    }
+
+    fn generate_bricks_below(num_bricks: usize, max_z: u32, seed: u64) -> String {
+        let mut rng = TestRng::new(seed);
+        let mut next = |modulus: u32| rng.below(modulus as u64) as u32;
+        let mut occupied = HashSet::<(u32,u32,u32)>::new();
+        let mut lines = Vec::<String>::new();
+        while lines.len() < num_bricks {
+            let (x, y, z) = (next(10), next(10), next(max_z) + 1);
+            let len = next(4);
+            let (p2x, p2y, p2z) = match next(3) {
+                0 => ((x + len).min(9), y, z),
+                1 => (x, (y + len).min(9), z),
+                _ => (x, y, z + len),
+            };
+            let cells = BrickPile::cells((x,y,z), (p2x,p2y,p2z)).collect::<Vec<_>>();
+            if cells.iter().any(|c| occupied.contains(c)) {
+                continue;
+            }
+            occupied.extend(cells);
+            lines.push(format!("{},{},{}~{},{},{}", x, y, z, p2x, p2y, p2z));
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    fn day22_brick_pile_matches_fallen_bricks() {
+        let input = input_generator(TEST_INPUT);
+        let pile = BrickPile::new(TEST_INPUT);
+        assert_eq!(pile.len(), 7);
+        for (brick_id, p1, p2) in input.brick_locations.iter() {
+            assert_eq!(pile.brick(*brick_id), Some((*p1,*p2)));
+            assert_eq!(pile.bricks_below(*brick_id), input.adjacent_bricks[*brick_id].below_set);
+            assert_eq!(pile.bricks_above(*brick_id), input.adjacent_bricks[*brick_id].above_set);
+        }
+    }
+
+    #[test]
+    fn day22_brick_pile_export() {
+        let pile = BrickPile::new(TEST_INPUT);
+        let exported = pile.to_string();
+        assert_eq!(exported,
+"1,0,1~1,2,1
+0,0,2~2,0,2
+0,2,2~2,2,2
+0,0,3~0,2,3
+2,0,3~2,2,3
+0,1,4~2,1,4
+1,1,5~1,1,6");
+        let redropped = BrickPile::new(&exported);
+        assert_eq!(sorted_brick_coords(&redropped), sorted_brick_coords(&pile));
+    }
+
+    #[test]
+    fn day22_brick_pile_remove() {
+        let mut pile = BrickPile::new(TEST_INPUT);
+        let moved = pile.remove_bricks(&[5]);//F
+        assert_eq!(moved, vec![6]);
+        assert_eq!(pile.brick(5), None);
+        assert_eq!(pile.brick(6), Some(((1,1,2),(1,1,3))));//G falls through to A
+        assert_eq!(pile.bricks_below(6), HashSet::from([0]));
+        assert_eq!(pile.bricks_above(3), HashSet::new());
+        let moved = pile.remove_bricks(&[0]);//A
+        assert_eq!(moved, vec![1,2,6,3,4]);
+        assert_eq!(pile.brick(1), Some(((0,0,1),(2,0,1))));
+        assert_eq!(pile.brick(6), Some(((1,1,1),(1,1,2))));
+    }
+
+    #[test]
+    fn day22_brick_pile_add() {
+        let mut pile = BrickPile::new(TEST_INPUT);
+        assert_eq!(pile.add_brick((1,1,6),(1,1,6)), None);//overlaps G
+        let new_id = pile.add_brick((0,0,20),(2,0,20)).unwrap();
+        assert_eq!(new_id, 7);
+        assert_eq!(pile.brick(new_id), Some(((0,0,4),(2,0,4))));
+        assert_eq!(pile.bricks_below(new_id), HashSet::from([3,4]));
+        assert_eq!(pile.bricks_above(3), HashSet::from([5,7]));
+        /* Ends out of order on y only */
+        let new_id = pile.add_brick((0,2,20),(2,0,20)).unwrap();
+        assert_eq!(pile.brick(new_id), Some(((0,0,7),(2,2,7))));
+        assert_eq!(pile.bricks_below(new_id), HashSet::from([6]));
+        assert_eq!(pile.add_brick((2,2,21),(2,2,7)), None);//reaches down into it
+    }
+
+    #[test]
+    fn day22_brick_pile_incremental_equals_full_redrop() {
+        let mut pile = BrickPile::new(&generate_falling_bricks(300, 22));
+        for round in 0..10 {
+            let removed = pile.bricks()
+                .map(|(bid,_,_)| bid)
+                .filter(|bid| (bid + round) % 7 == 0)
+                .take(5)
+                .collect::<Vec<usize>>();
+            pile.remove_bricks(&removed);
+            let redropped = BrickPile::new(&pile.to_string());
+            assert_eq!(sorted_brick_coords(&pile), sorted_brick_coords(&redropped));
+            pile.add_brick((round as u32, 3, 400), (round as u32, 5, 400));
+        }
+    }
+
+    fn assert_same_fallen_bricks(lhs: &FallenBricks, rhs: &FallenBricks) {
+        assert_eq!(lhs.brick_locations, rhs.brick_locations);
+        assert_eq!(lhs.adjacent_bricks.len(), rhs.adjacent_bricks.len());
+        for (l, r) in lhs.adjacent_bricks.iter().zip(rhs.adjacent_bricks.iter()) {
+            assert_eq!(l.below_set, r.below_set);
+            assert_eq!(l.above_set, r.above_set);
+        }
+    }
+
+    #[test]
+    fn day22_height_map_input() {
+        let input = input_generator(TEST_INPUT);
+        let input_hm = input_generator_height_map(TEST_INPUT);
+        assert_same_fallen_bricks(&input, &input_hm);
+        assert_eq!(solve_day22_p1(&input_hm), 5);
+        assert_eq!(solve_day22_p2(&input_hm), 7);
+    }
+
+    #[test]
+    fn day22_height_map_generated_input() {
+        let falling = generate_dense_falling_bricks(500, 2023);
+        assert_same_fallen_bricks(&input_generator(&falling), &input_generator_height_map(&falling));
+    }
+
+    /* Run with: cargo test --release day22_bench_height_map -- --ignored --nocapture */
+    #[test]
+    #[ignore]
+    fn day22_bench_height_map() {
+        let falling = generate_dense_falling_bricks(12_500, 7);//~10x the puzzle input
+        let start = std::time::Instant::now();
+        let input = input_generator(&falling);
+        let t_space = start.elapsed();
+        let start = std::time::Instant::now();
+        let input_hm = input_generator_height_map(&falling);
+        let t_height_map = start.elapsed();
+        println!("HashMap space: {:?}, height map: {:?}", t_space, t_height_map);
+        assert_same_fallen_bricks(&input, &input_hm);
+    }
 }
@@ -60,85 +60,128 @@ fn parse_schematic_symbol(input: &str) -> IResult<&str, SchematicEntry> {
         .parse(input)
 }
 
-#[aoc(day3, part1)]
-pub fn solve_part1(input: &Array2<SchematicEntry>) -> u64 {
-    let mut ans = 0;
-    let mut fsaw_symbol = false;
-    let mut char_stack = Vec::<char>::new();
-    let mut char_stack_row_num = 0;//track where we're collecting num chars
-    let (outer_max, inner_max) = input.dim();
-    for j in 0..outer_max {
-        for i in 0..inner_max {
-            match input[[j,i]] {
-                SchematicEntry::Number(num_char) => {
-                    if char_stack.is_empty() {//started reading num chars
-                        char_stack_row_num = j;
-                    }
-                    if char_stack_row_num != j {//prevent errors at line breaks
-                        if fsaw_symbol {
-                            ans += consume_char_stack_to_u64(&mut char_stack);
-                        } else {
-                            char_stack.clear();
-                        }
-                        char_stack_row_num = j;
-                        fsaw_symbol = false;
-                    }
-                    char_stack.push(num_char);
-                    if !fsaw_symbol {
-                        fsaw_symbol |= check_above_and_below_for_symbol(&input, (j,i));
-                        if (i > 0) && (char_stack.len() == 1) {
-                            fsaw_symbol |= check_above_and_below_for_symbol(&input, (j,i - 1));
-                        }
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct SchematicNumber {
+    pub value: u64,
+    pub row: usize,
+    pub cols: (usize, usize),//first and one past the last column
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct SchematicSymbol {
+    pub symbol: char,
+    pub coords: (usize, usize),
+}
+
+/* Numbers and symbols of the schematic with the index of which ones touch, diagonals included */
+#[derive(Clone,Debug)]
+pub struct Schematic {
+    numbers: Vec<SchematicNumber>,
+    symbols: Vec<SchematicSymbol>,
+    number_to_symbols: Vec<Vec<usize>>,
+    symbol_to_numbers: Vec<Vec<usize>>,
+}
+
+impl Schematic {
+    pub fn new(input: &Array2<SchematicEntry>) -> Self {
+        let (rows, cols) = input.dim();
+        let mut numbers = Vec::<SchematicNumber>::new();
+        let mut symbols = Vec::<SchematicSymbol>::new();
+        let mut symbol_ids = Array2::<Option<usize>>::from_elem((rows, cols), None);
+        for ((row, col), entry) in input.indexed_iter() {
+            match entry {
+                SchematicEntry::Number(digit) => {
+                    let digit = digit.to_digit(10).unwrap() as u64;
+                    match numbers.last_mut() {
+                        Some(number) if number.row == row && number.cols.1 == col => {
+                            number.value = number.value * 10 + digit;
+                            number.cols.1 += 1;
+                        },
+                        _ => numbers.push(SchematicNumber {value: digit, row, cols: (col, col + 1)}),
                     }
                 },
-                _ => {
-                    if char_stack.len() > 0 {//after parsing nums
-                        if char_stack_row_num == j {//prevent errors at line breaks
-                            fsaw_symbol |= check_above_and_below_for_symbol(&input, (j,i));
-                        } 
-                        if fsaw_symbol {
-                            let inc = consume_char_stack_to_u64(&mut char_stack);
-                            ans += inc;
-                            fsaw_symbol = false;
-                        } else {
-                            char_stack.clear();
-                        }
-                    }
+                SchematicEntry::Symbol(symbol) => {
+                    symbol_ids[[row, col]] = Some(symbols.len());
+                    symbols.push(SchematicSymbol {symbol: *symbol, coords: (row, col)});
                 },
+                SchematicEntry::Dot => {},
             }
         }
+        let mut symbol_to_numbers = vec![Vec::<usize>::new(); symbols.len()];
+        let number_to_symbols = numbers.iter()
+            .enumerate()
+            .map(|(number_id, number)| {
+                let row_range = number.row.saturating_sub(1)..(number.row + 2).min(rows);
+                let col_range = number.cols.0.saturating_sub(1)..(number.cols.1 + 1).min(cols);
+                let touching = symbol_ids.slice(s![row_range, col_range])
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<usize>>();
+                for symbol_id in touching.iter() {
+                    symbol_to_numbers[*symbol_id].push(number_id);
+                }
+                touching
+            })
+            .collect();
+        Schematic {numbers, symbols, number_to_symbols, symbol_to_numbers}
+    }
+
+    pub fn numbers(&self) -> &[SchematicNumber] {
+        &self.numbers
+    }
+
+    pub fn symbols(&self) -> &[SchematicSymbol] {
+        &self.symbols
+    }
+
+    pub fn symbols_next_to(&self, number_id: usize) -> impl Iterator<Item = &SchematicSymbol> {
+        self.number_to_symbols[number_id].iter().map(|id| &self.symbols[*id])
+    }
+
+    pub fn numbers_next_to(&self, symbol_id: usize) -> impl Iterator<Item = &SchematicNumber> {
+        self.symbol_to_numbers[symbol_id].iter().map(|id| &self.numbers[*id])
     }
-    ans
-}
 
-#[inline]
-fn consume_char_stack_to_u64(char_stack: &mut Vec<char>) -> u64 {
-    let my_str = char_stack.iter().collect::<String>();
-    char_stack.clear();
-    my_str.parse::<u64>().expect("Non-numeric char in stack")
+    /* Numbers next to at least one symbol */
+    pub fn part_numbers(&self) -> impl Iterator<Item = &SchematicNumber> {
+        self.numbers.iter()
+            .zip(self.number_to_symbols.iter())
+            .filter(|(_, symbols)| !symbols.is_empty())
+            .map(|(number, _)| number)
+    }
+
+    /* Every `symbol` with exactly `k` numbers next to it, together with those numbers */
+    pub fn symbols_with_neighbours(&self, symbol: char, k: usize)
+        -> impl Iterator<Item = (&SchematicSymbol, Vec<&SchematicNumber>)>
+    {
+        self.symbols.iter()
+            .enumerate()
+            .filter(move |(id, sym)| sym.symbol == symbol && self.symbol_to_numbers[*id].len() == k)
+            .map(|(id, sym)| (sym, self.numbers_next_to(id).collect()))
+    }
+
+    /* Product of the two numbers next to every '*' with exactly two */
+    pub fn gear_ratios(&self) -> impl Iterator<Item = u64> + '_ {
+        self.symbols_with_neighbours('*', 2)
+            .map(|(_, numbers)| numbers.iter().map(|number| number.value).product())
+    }
 }
 
-fn check_above_and_below_for_symbol(input: &Array2<SchematicEntry>, (x,y): (usize,usize)) -> bool {
-    let start = if x == 0 {
-        0
-    } else {
-        (x - 1).clamp(0, input.dim().0)
-    };
-    let end = (x + 2).clamp(0, input.dim().0);
-    input.slice(s![start..end, y])
-        .iter()
-        .any(|el| {
-            match el {
-                SchematicEntry::Symbol(_) => true,
-                _ => false,
-            }
-        })
+#[aoc(day3, part1)]
+pub fn solve_part1(input: &Array2<SchematicEntry>) -> u64 {
+    Schematic::new(input)
+        .part_numbers()
+        .map(|number| number.value)
+        .sum()
 }
 
-// 78915902
-//#[aoc(day3, part2)]
-//pub fn solve_part2(input: &[Vec<i64>]) -> i64 {
-//}
+#[aoc(day3, part2)]
+pub fn solve_part2(input: &Array2<SchematicEntry>) -> u64 {
+    Schematic::new(input)
+        .gear_ratios()
+        .sum()
+}
 
 #[cfg(test)]
 mod tests {
@@ -191,33 +234,23 @@ mod tests {
     }
 
     #[test]
-    fn day3_char_stack_to_u64() {
-        let mut input = vec!['1', '2', '3'];
-        let ans = consume_char_stack_to_u64(&mut input);
-        assert_eq!(ans, 123);
-        assert_eq!(input.is_empty(), true);
-    }
-
-    #[test]
-    fn day3_check_above_and_below_for_symbol_1() {
-        let input = input_generator(TEST_INPUT);
-        let ans1 = check_above_and_below_for_symbol(&input, (0,1));
-        let ans2 = check_above_and_below_for_symbol(&input, (1,1));
-        let ans3 = check_above_and_below_for_symbol(&input, (9,1));
-        let ans4 = check_above_and_below_for_symbol(&input, (0,3));
-        let ans5 = check_above_and_below_for_symbol(&input, (1,3));
-        let ans6 = check_above_and_below_for_symbol(&input, (2,3));
-        let ans7 = check_above_and_below_for_symbol(&input, (7,5));
-        assert_eq!(ans1, false);
-        assert_eq!(ans2, false);
-        assert_eq!(ans3, false);
-        assert_eq!(ans4, true);
-        assert_eq!(ans5, true);
-        assert_eq!(ans6, true);
-        assert_eq!(ans7, true);
+    fn day3_schematic_index() {
+        let schematic = Schematic::new(&input_generator(TEST_INPUT));
+        assert_eq!(schematic.numbers().len(), 10);
+        assert_eq!(schematic.numbers()[0], SchematicNumber {value: 467, row: 0, cols: (0, 3)});
+        assert_eq!(schematic.numbers()[9], SchematicNumber {value: 598, row: 9, cols: (5, 8)});
+        assert_eq!(schematic.symbols().len(), 6);
+        assert_eq!(schematic.symbols()[0], SchematicSymbol {symbol: '*', coords: (1, 3)});
+        /* 114 and 58 touch nothing */
+        assert_eq!(schematic.symbols_next_to(1).count(), 0);
+        assert_eq!(schematic.symbols_next_to(2).map(|sym| sym.symbol).collect::<Vec<char>>(), vec!['*']);
+        assert_eq!(schematic.numbers_next_to(0).map(|number| number.value).collect::<Vec<u64>>(), vec![467, 35]);
+        let lonely = schematic.symbols_with_neighbours('*', 1)
+            .map(|(sym, numbers)| (sym.coords, numbers[0].value))
+            .collect::<Vec<((usize, usize), u64)>>();
+        assert_eq!(lonely, vec![((4, 3), 617)]);
     }
 
-
     #[test]
     fn day3_solve_p1_1() {
         let input = input_generator(TEST_INPUT);
@@ -254,7 +287,11 @@ mod tests {
         let ans = solve_part1(&input);
         assert_eq!(ans,4);
     }
-//    #[test]
-//    fn day3_solve_p2() {
-//    }
+
+    #[test]
+    fn day3_solve_p2() {
+        let input = input_generator(TEST_INPUT);
+        let ans = solve_part2(&input);
+        assert_eq!(ans, 467835);
+    }
 }
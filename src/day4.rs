@@ -1,5 +1,4 @@
 use core::panic;
-use std::collections::{HashSet, VecDeque};
 use nom::{
     Parser,
     IResult,
@@ -9,12 +8,55 @@ use nom::{
     multi::{separated_list0, separated_list1}, sequence::{separated_pair, preceded},
 };
 
+/* Set of numbers below 128 */
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct NumberSet(u128);
+
+impl NumberSet {
+    pub fn insert(&mut self, num: u32) {
+        assert!(num < 128, "Scratch card numbers must be below 128, got {}", num);
+        self.0 |= 1u128 << num;
+    }
+
+    pub fn contains(&self, num: &u32) -> bool {
+        *num < 128 && self.0 >> num & 1 == 1
+    }
+
+    pub fn len(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn intersection(&self, other: &NumberSet) -> NumberSet {
+        NumberSet(self.0 & other.0)
+    }
+}
+
+impl FromIterator<u32> for NumberSet {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut set = NumberSet::default();
+        for num in iter {
+            set.insert(num);
+        }
+        set
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct ScratchCard {
     card_num: u32,
-    winning_numbers: HashSet<u32>,
-    my_numbers: HashSet<u32>,
+    winning_numbers: NumberSet,
+    my_numbers: NumberSet,
+}
+
+impl ScratchCard {
+    pub fn matches(&self) -> u32 {
+        self.my_numbers.intersection(&self.winning_numbers).len()
+    }
 }
 
 #[aoc_generator(day4)]
@@ -44,77 +86,49 @@ fn parse_num(input: &str) -> IResult<&str, u32> {
     Ok((out,num))
 }
 
-fn parse_scratch_nums(input: &str) -> IResult<&str, (HashSet<u32>, HashSet<u32>)> {
+fn parse_scratch_nums(input: &str) -> IResult<&str, (NumberSet, NumberSet)> {
     separated_pair(parse_nums_to_hast_set, tag(" | "), parse_nums_to_hast_set)
         .parse(input)
 }
 
-fn parse_nums_to_hast_set(input: &str) -> IResult<&str, HashSet<u32>> {
+fn parse_nums_to_hast_set(input: &str) -> IResult<&str, NumberSet> {
     separated_list1(
         take_while1(char::is_whitespace),
         alt((parse_num, preceded(take_while1(char::is_whitespace), parse_num)))
         )
-        .map(|el| el.into_iter().collect::<HashSet<u32>>())
+        .map(|el| el.into_iter().collect::<NumberSet>())
         .parse(input)
 }
 
 #[aoc(day4, part1)]
 pub fn solve_part1(input: &[ScratchCard]) -> u32 {
     input.iter()
-        .map(|el| el.my_numbers.intersection(&el.winning_numbers).count())
+        .map(ScratchCard::matches)
         .map(|x| if x == 0 {0} else {1u32 << (x - 1)})
         .sum()
 }
 
-#[derive(Debug,Default)]
-struct ScratchCardSpawnTracker {
-    queue: VecDeque<u32>,
-}
-
-#[allow(dead_code)]
-impl ScratchCardSpawnTracker {
-   fn pop_front(&mut self) -> Option<u32> {
-       self.queue.pop_front()
-   } 
-
-   fn push_back(&mut self, val: u32) {
-       self.queue.push_back(val)
-   }
-
-   /* magic_fn(2,2) for self.queue = [1,1,1]
-    * => self.queue = [3,3,1]
-    *
-    * magic_fn(4,1) for self.queue = []
-    * => self.queue = [1,1,1,1]
-    *
-    * magic_fn(4,2) for self.queue = [5,2]
-    * => self.queue = [7,4,2,2]
-    * */
-   fn magic_fn(&mut self, affected: usize, num_cards: u32) {
-       for idx in 0..affected {
-           if idx < self.queue.len() {
-               self.queue[idx] += num_cards;
-           } else {
-               self.queue.push_back(num_cards);
-           }
-       }
-   }
+/* Number of copies of every card, the original included. Card i with m matches adds its
+ * copies to cards i+1..=i+m, kept as a difference array. */
+pub fn card_copies(input: &[ScratchCard]) -> Vec<u32> {
+    let mut diff = vec![0i64; input.len() + 1];
+    let mut won = 0i64;
+    input.iter()
+        .enumerate()
+        .map(|(idx, card)| {
+            won += diff[idx];
+            let copies = 1 + won;
+            let end = (idx + 1 + card.matches() as usize).min(input.len());
+            diff[idx + 1] += copies;
+            diff[end] -= copies;
+            copies as u32
+        })
+        .collect()
 }
 
 #[aoc(day4, part2)]
 pub fn solve_part2(input: &[ScratchCard]) -> u32 {
-    let mut queue = ScratchCardSpawnTracker::default();
-    input.iter()
-        .map(|el| el.my_numbers.intersection(&el.winning_numbers).count())
-        .map(|affected_cards| {
-            let num_cards = match queue.pop_front() {
-                Some(copies) => copies + 1,
-                None => 1,//just the original
-            };
-            queue.magic_fn(affected_cards, num_cards);
-            num_cards
-        })
-        .sum::<u32>()
+    card_copies(input).into_iter().sum()
 }
 
 #[cfg(test)]
@@ -175,5 +189,15 @@ Card   2: 92 97 39 23 25 40 33 70 55 77 | 25 70 23 91 45 60 34 56 82  6  9 62 24
         let ans = solve_part2(&input);
         assert_eq!(ans, 30)
     }
+
+    #[test]
+    fn day4_card_copies() {
+        let input = input_generator(TEST_INPUT);
+        assert_eq!(input.iter().map(ScratchCard::matches).collect::<Vec<u32>>(), vec![4, 2, 2, 1, 0, 0]);
+        assert_eq!(card_copies(&input), vec![1, 2, 4, 8, 14, 1]);
+        /* Wins past the last card are dropped */
+        let input = input_generator("Card 1: 1 2 | 1 2\nCard 2: 3 4 | 3 5");
+        assert_eq!(card_copies(&input), vec![1, 2]);
+    }
 }
 
@@ -1,4 +1,6 @@
 use std::iter::zip;
+use std::ops::RangeInclusive;
+use num::{BigUint, One, Zero};
 use nom::{
     Parser,
     IResult,
@@ -10,8 +12,8 @@ use nom::{
 #[derive(Debug,PartialEq,Eq)]
 #[allow(dead_code)]
 pub struct BoatRacePair {
-    time: u64,
-    distance: u64,
+    time: BigUint,
+    distance: BigUint,
 }
 
 #[aoc_generator(day6)]
@@ -29,38 +31,38 @@ pub fn input_generator(input: &str) -> Vec<BoatRacePair> {
     }
 }
 
-fn parse_times(input: &str) -> IResult<&str, Vec<u64>> {
+fn parse_times(input: &str) -> IResult<&str, Vec<BigUint>> {
     preceded(tag("Time:"), parse_nums)
         .parse(input)
 }
 
-fn parse_distances(input: &str) -> IResult<&str, Vec<u64>> {
+fn parse_distances(input: &str) -> IResult<&str, Vec<BigUint>> {
     preceded(tag("Distance:"), parse_nums)
         .parse(input)
 }
 
-fn parse_nums(input: &str) -> IResult<&str, Vec<u64>> {
+fn parse_nums(input: &str) -> IResult<&str, Vec<BigUint>> {
     many1(preceded(take_while1(char::is_whitespace), parse_one_num))
         .parse(input)
 }
 
-fn parse_one_num(input: &str) -> IResult<&str, u64> {
-    take_while1(char::is_numeric)
-        .map(|x: &str| x.to_string().parse::<u64>().unwrap())
+fn parse_one_num(input: &str) -> IResult<&str, BigUint> {
+    take_while1(|c: char| c.is_ascii_digit())
+        .map(|x: &str| x.parse::<BigUint>().unwrap())
         .parse(input)
 }
 
 fn calc_num_possible_winning_boat_button_push_times(input: &BoatRacePair) -> u64 {
-    (0..input.time).into_iter()
+    num::range(BigUint::zero(), input.time.clone())
         .map(|num| {
-            num * (input.time - num)
+            &num * (&input.time - &num)
         })
         .filter(|x| x > &input.distance)
         .count() as u64
 }
 
-#[aoc(day6, part1)]
-pub fn solve_part1(input: &[BoatRacePair]) -> u64 {
+#[allow(dead_code)]
+fn solve_part1_brute_force(input: &[BoatRacePair]) -> u64 {
     input.iter()
         .map(|el| {
             calc_num_possible_winning_boat_button_push_times(el)
@@ -68,53 +70,83 @@ pub fn solve_part1(input: &[BoatRacePair]) -> u64 {
     .reduce(|acc,el| acc * el).unwrap()
 }
 
-fn join_nums_for_p2(input: &[BoatRacePair]) -> BoatRacePair {
-    let new_time: String = input.iter()
-        .map(|pair| pair.time.to_string())
-        .reduce(|mut acc,el| {
-            acc.push_str(el.as_str());
-            acc
-        }).unwrap();
-    let new_time = new_time.parse::<u64>().unwrap();
-    let new_distance = input.iter()
-        .map(|pair| pair.distance.to_string())
-        .reduce(|mut acc,el| {
-            acc.push_str(el.as_str());
-            acc
-        }).unwrap();
-    let new_distance = new_distance.parse::<u64>().unwrap();
-    BoatRacePair {
-        time: new_time,
-        distance: new_distance
+/* Concatenates the digits of all races into one race */
+fn join_nums_for_p2(input: &[BoatRacePair]) -> (BigUint, BigUint) {
+    let join = |nums: &mut dyn Iterator<Item = &BigUint>| {
+        nums.map(|num| num.to_string())
+            .collect::<String>()
+            .parse::<BigUint>()
+            .unwrap()
+    };
+    (join(&mut input.iter().map(|pair| &pair.time)),
+        join(&mut input.iter().map(|pair| &pair.distance)))
+}
+
+/* Button push times from `first` to `last` that beat the record */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct WinningRange {
+    pub first: BigUint,
+    pub last: BigUint,
+}
+
+impl WinningRange {
+    pub fn range(&self) -> RangeInclusive<BigUint> {
+        self.first.clone()..=self.last.clone()
+    }
+
+    /* Never zero, winning_range returns None instead of an empty range */
+    pub fn count(&self) -> BigUint {
+        &self.last - &self.first + 1u32
     }
 }
 
-/* Solve using quadratic formula on f(x) = x^2 - x*t + d
- * All nums between the zeros are answers.
+/* Exact solution of x * (t - x) > d, the zeros of x^2 - x*t + d are (t -+ sqrt(t^2 - 4d)) / 2.
+ * The integer square root puts the first zero within one of the real one, the boundary is
+ * corrected by checking its neighbours. The range is symmetric around t / 2.
  * x : button push time
  * t : maximum time allowed
  * d : distance to beat
  * */
-fn calc_num_possible_winning_boat_button_push_times_p2(input: &BoatRacePair) -> u64 {
-    let BoatRacePair {time: t, distance: d} = input;
-    let discrimianant = ((t * t - 4 * d) as f64).sqrt();
-    let x1 = (-(*t as f64) + discrimianant) / 2.0; 
-    let x2 = (-(*t as f64) - discrimianant) / 2.0; 
-    if x1 < x2 {
-        return (x2.ceil() - x1.ceil()) as u64;
+pub fn winning_range(time: &BigUint, distance: &BigUint) -> Option<WinningRange> {
+    let wins = |x: &BigUint| x * (time - x) > *distance;
+    let half = time / 2u32;
+    if !wins(&half) {
+        return None;
+    }
+    let discriminant = time * time - distance * 4u32;// t^2 > 4d since half wins
+    let mut first = (time - discriminant.sqrt()) / 2u32;
+    while !wins(&first) {
+        first += 1u32;
+    }
+    while !first.is_zero() && wins(&(&first - 1u32)) {
+        first -= 1u32;
     }
-    (x1.ceil() - x2.ceil()) as u64
+    let last = time - &first;
+    Some(WinningRange {first, last})
+}
+
+/* Number of winning button push times */
+pub fn count_winning_push_times(time: &BigUint, distance: &BigUint) -> BigUint {
+    winning_range(time, distance).map_or(BigUint::zero(), |range| range.count())
+}
+
+#[aoc(day6, part1)]
+pub fn solve_part1(input: &[BoatRacePair]) -> BigUint {
+    input.iter()
+        .map(|el| count_winning_push_times(&el.time, &el.distance))
+        .fold(BigUint::one(), |acc, el| acc * el)
 }
 
 #[aoc(day6, part2)]
-pub fn solve_part2(input: &[BoatRacePair]) -> u64 {
-    let new_input = join_nums_for_p2(input);
-    calc_num_possible_winning_boat_button_push_times_p2(&new_input)
+pub fn solve_part2(input: &[BoatRacePair]) -> BigUint {
+    let (time, distance) = join_nums_for_p2(input);
+    count_winning_push_times(&time, &distance)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num::ToPrimitive;
     const TEST_INPUT: &str =
 "Time:      7  15   30
 Distance:  9  40  200";
@@ -123,13 +155,15 @@ Distance:  9  40  200";
     fn day6_parser() {
         let input = input_generator(TEST_INPUT);
         assert_eq!(input.len(),3);
-        assert_eq!(input[0].time,7);
-        assert_eq!(input[2].distance,200);
+        assert_eq!(input[0].time,BigUint::from(7u32));
+        assert_eq!(input[2].distance,BigUint::from(200u32));
+        let input = input_generator("Time: 123456789012345678901234567890\nDistance: 1");
+        assert_eq!(input[0].time.to_string(), "123456789012345678901234567890");
     }
     
     #[test]
     fn day6_function_with_the_long_name() {
-        let input = BoatRacePair {time: 7, distance: 9};
+        let input = BoatRacePair {time: 7u32.into(), distance: 9u32.into()};
         let ans = calc_num_possible_winning_boat_button_push_times(&input);
         assert_eq!(ans,4);
     }
@@ -138,17 +172,66 @@ Distance:  9  40  200";
     fn day6_join_nums_for_p2() {
         let input = input_generator(TEST_INPUT);
         let ans = join_nums_for_p2(&input);
-        assert_eq!(ans, BoatRacePair {time: 71530, distance: 940200})
+        assert_eq!(ans, (BigUint::from(71530u32), BigUint::from(940200u32)))
     }
     
-    //#[test]
-    //fn test_solve_day6p1() {
-    //}
+    #[test]
+    fn day6_solve_p1() {
+        let input = input_generator(TEST_INPUT);
+        assert_eq!(solve_part1(&input), BigUint::from(288u32));
+        assert_eq!(solve_part1_brute_force(&input), 288);
+        /* Every push time but 0 and t wins, the product is past u64 */
+        let input = input_generator("Time: 4294967298 4294967298\nDistance: 0 0");
+        assert_eq!(solve_part1(&input), BigUint::from((1u64 << 32) + 1).pow(2));
+    }
 
     #[test]
     fn day6_solve_p2() {
         let input = input_generator(TEST_INPUT);
         let ans = solve_part2(&input);
-        assert_eq!(ans,71503);
+        assert_eq!(ans, BigUint::from(71503u32));
+        let (time, distance) = join_nums_for_p2(&input);
+        let range = winning_range(&time, &distance).unwrap();
+        assert_eq!(range.range(), BigUint::from(14u32)..=BigUint::from(71516u32));
+    }
+
+    #[test]
+    fn day6_exact_matches_brute_force() {
+        for time in 0..60u64 {
+            for distance in 0..time * time / 4 + 3 {
+                let race = BoatRacePair {time: time.into(), distance: distance.into()};
+                let brute = (0..=time).filter(|x| x * (time - x) > distance).collect::<Vec<u64>>();
+                let range = winning_range(&time.into(), &distance.into());
+                assert_eq!(range.as_ref().map(|range| (range.first.to_u64().unwrap(), range.last.to_u64().unwrap())),
+                    brute.first().zip(brute.last()).map(|(first, last)| (*first, *last)),
+                    "{:?}", race);
+                assert_eq!(count_winning_push_times(&time.into(), &distance.into()),
+                    BigUint::from(calc_num_possible_winning_boat_button_push_times(&race)));
+            }
+        }
+    }
+
+    #[test]
+    fn day6_exact_large_races() {
+        let time = "123456789012345678901234567890123".parse::<BigUint>().unwrap();
+        let wins = |x: &BigUint, distance: &BigUint| x * (&time - x) > *distance;
+        for offset in [1u32, 2, 3, 1000] {
+            /* The record is set by holding x0, so x0 + 1 is the first winning time */
+            let x0 = &time / 3u32 + offset;
+            let distance = &x0 * (&time - &x0);
+            let range = winning_range(&time, &distance).unwrap();
+            assert_eq!(range.first, &x0 + 1u32);
+            assert_eq!(range.last, &time - &x0 - 1u32);
+            assert_eq!(range.count(), &time - &x0 * 2u32 - 1u32);
+            /* One below the record */
+            let range = winning_range(&time, &(&distance - 1u32)).unwrap();
+            assert_eq!(range.first, x0);
+            assert!(!wins(&(&range.first - 1u32), &(&distance - 1u32)));
+        }
+        /* Only the middle wins, then nothing */
+        let half = &time / 2u32;
+        let best = &half * (&time - &half);
+        assert_eq!(count_winning_push_times(&time, &(&best - 1u32)), BigUint::from(2u32));
+        assert_eq!(winning_range(&time, &best), None);
     }
 }
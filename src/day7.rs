@@ -1,5 +1,5 @@
 use core::panic;
-use std::{cmp::Ordering, collections::HashMap, iter::zip};
+use std::{cmp::Ordering, iter::zip};
 
 use nom::{
     Parser,
@@ -8,22 +8,11 @@ use nom::{
     character::complete::one_of,
     combinator::{all_consuming, value},
     bytes::complete::{tag,take_while1},
-    multi::{count,separated_list1}, sequence::separated_pair,
+    multi::{count,separated_list1}, sequence::{pair,preceded,separated_pair},
 };
 
-#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
-enum HandType {
-    HighCard,//lowest
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,//highest
-}
-
 #[derive(Clone,Copy,Debug,Hash,PartialEq,Eq,PartialOrd,Ord)]
-enum CamelCard {
+pub enum CamelCard {
     Num(u8),//smallest
     T,
     J,
@@ -32,6 +21,20 @@ enum CamelCard {
     A,//largest
 }
 
+impl CamelCard {
+    #[inline]
+    const fn index(self) -> usize {
+        match self {
+            CamelCard::Num(n) => n as usize - 2,
+            CamelCard::T => 8,
+            CamelCard::J => 9,
+            CamelCard::Q => 10,
+            CamelCard::K => 11,
+            CamelCard::A => 12,
+        }
+    }
+}
+
 #[derive(Clone,Debug,PartialEq,Eq)]
 #[allow(dead_code)]
 pub struct CamelCardsHand {
@@ -39,158 +42,257 @@ pub struct CamelCardsHand {
     bid_value: u64,
 }
 
-impl PartialOrd for CamelCardsHand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(&other))
+/* Five cards with suits, written like "TH JH QH KH AH" */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct PokerHand {
+    cards: Vec<CamelCard>,
+    suits: Vec<char>,
+}
+
+/* Anything the rules can rank */
+pub trait DealtCards {
+    fn cards(&self) -> &[CamelCard];
+    /* None if the cards have no suits, then there are no flushes */
+    fn suits(&self) -> Option<&[char]>;
+}
+
+impl DealtCards for CamelCardsHand {
+    fn cards(&self) -> &[CamelCard] {
+        &self.cards
+    }
+
+    fn suits(&self) -> Option<&[char]> {
+        None
     }
 }
 
-impl Ord for CamelCardsHand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let self_type = self.hand_type_p1();
-        let other_type = other.hand_type_p1();
-        if self_type > other_type {
-            return Ordering::Greater;
-        }
-        if self_type < other_type {
-            return Ordering::Less;
+impl DealtCards for PokerHand {
+    fn cards(&self) -> &[CamelCard] {
+        &self.cards
+    }
+
+    fn suits(&self) -> Option<&[char]> {
+        Some(&self.suits)
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum HandCategory {
+    Groups(&'static [u8]),//sizes of the groups of equal cards, largest first
+    Straight,//five consecutive ranks, the highest card may also go below the lowest
+    Flush,//five cards of one suit
+    StraightFlush,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum TieBreak {
+    HandOrder,//first differing card in the order the cards were dealt
+    Grouped,//largest groups first, higher ranks first within groups, like poker kickers
+}
+
+/* Rank order of the cards, wild cards and the hand categories of a game.
+ * Wild cards join the largest group and fill gaps in straights and flushes, but keep their
+ * own rank for tie-breaks. */
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct CamelRules {
+    pub card_order: [CamelCard; 13],//lowest first
+    pub wild: &'static [CamelCard],
+    pub categories: &'static [HandCategory],//lowest first
+    pub tie_break: TieBreak,
+}
+
+const CAMEL_CATEGORIES: [HandCategory; 7] = [
+    HandCategory::Groups(&[1, 1, 1, 1, 1]),
+    HandCategory::Groups(&[2, 1, 1, 1]),
+    HandCategory::Groups(&[2, 2, 1]),
+    HandCategory::Groups(&[3, 1, 1]),
+    HandCategory::Groups(&[3, 2]),
+    HandCategory::Groups(&[4, 1]),
+    HandCategory::Groups(&[5]),
+];
+
+const POKER_CATEGORIES: [HandCategory; 9] = [
+    HandCategory::Groups(&[1, 1, 1, 1, 1]),
+    HandCategory::Groups(&[2, 1, 1, 1]),
+    HandCategory::Groups(&[2, 2, 1]),
+    HandCategory::Groups(&[3, 1, 1]),
+    HandCategory::Straight,
+    HandCategory::Flush,
+    HandCategory::Groups(&[3, 2]),
+    HandCategory::Groups(&[4, 1]),
+    HandCategory::StraightFlush,
+];
+
+const ACE_HIGH: [CamelCard; 13] = [
+    CamelCard::Num(2), CamelCard::Num(3), CamelCard::Num(4), CamelCard::Num(5),
+    CamelCard::Num(6), CamelCard::Num(7), CamelCard::Num(8), CamelCard::Num(9),
+    CamelCard::T, CamelCard::J, CamelCard::Q, CamelCard::K, CamelCard::A,
+];
+
+impl CamelRules {
+    pub const PART1: CamelRules = CamelRules {
+        card_order: ACE_HIGH,
+        wild: &[],
+        categories: &CAMEL_CATEGORIES,
+        tie_break: TieBreak::HandOrder,
+    };
+
+    /* Jokers are wild and the weakest card */
+    pub const PART2: CamelRules = CamelRules {
+        card_order: [
+            CamelCard::J, CamelCard::Num(2), CamelCard::Num(3), CamelCard::Num(4),
+            CamelCard::Num(5), CamelCard::Num(6), CamelCard::Num(7), CamelCard::Num(8),
+            CamelCard::Num(9), CamelCard::T, CamelCard::Q, CamelCard::K, CamelCard::A,
+        ],
+        wild: &[CamelCard::J],
+        categories: &CAMEL_CATEGORIES,
+        tie_break: TieBreak::HandOrder,
+    };
+
+    /* Standard five-card poker */
+    pub const POKER: CamelRules = CamelRules {
+        card_order: ACE_HIGH,
+        wild: &[],
+        categories: &POKER_CATEGORIES,
+        tie_break: TieBreak::Grouped,
+    };
+
+    #[inline]
+    pub fn rank(&self, card: CamelCard) -> u8 {
+        self.card_order.iter()
+            .position(|ordered| *ordered == card)
+            .expect("Card is missing from the rank order") as u8
+    }
+
+    /* Rank of every card by CamelCard::index */
+    fn rank_table(&self) -> [u8; 13] {
+        let mut ranks = [0u8; 13];
+        for (rank, card) in self.card_order.iter().enumerate() {
+            ranks[card.index()] = rank as u8;
         }
-        let my_iter = zip(self.cards.iter(), other.cards.iter());
-        for (my_card, other_card) in my_iter {
-            if my_card > other_card {
-                return Ordering::Greater;
-            }
-            if my_card < other_card {
-                return Ordering::Less;
+        ranks
+    }
+
+    fn is_wild(&self, card: CamelCard) -> bool {
+        self.wild.contains(&card)
+    }
+
+    /* Index of the highest category the hand fits into, None if it fits none */
+    pub fn category<H: DealtCards>(&self, hand: &H) -> Option<usize> {
+        self.category_ranked(&self.rank_table(), hand)
+    }
+
+    fn category_ranked<H: DealtCards>(&self, ranks: &[u8; 13], hand: &H) -> Option<usize> {
+        let cards = hand.cards();
+        let mut counts = [0u8; 13];
+        let mut num_wild = 0;
+        for card in cards.iter() {
+            if self.is_wild(*card) {
+                num_wild += 1;
+            } else {
+                counts[ranks[card.index()] as usize] += 1;
             }
         }
-        Ordering::Equal
+        let mut groups = counts;
+        groups.sort_unstable_by(|a, b| b.cmp(a));
+        groups[0] += num_wild;
+        let groups = &groups[..groups.iter().take_while(|count| **count > 0).count()];
+        let is_straight = cards.len() == 5 && self.fits_straight(&counts);
+        let is_flush = cards.len() == 5 && hand.suits().is_some_and(|suits| {
+            let mut suits = zip(cards.iter(), suits.iter())
+                .filter(|(card, _)| !self.is_wild(**card))
+                .map(|(_, suit)| suit);
+            suits.next().is_none_or(|first| suits.all(|suit| suit == first))
+        });
+        self.categories.iter()
+            .rposition(|category| match category {
+                HandCategory::Groups(sizes) => groups == *sizes,
+                HandCategory::Straight => is_straight,
+                HandCategory::Flush => is_flush,
+                HandCategory::StraightFlush => is_straight && is_flush,
+            })
     }
-}
 
-#[allow(dead_code)]
-impl CamelCardsHand {
-    fn cmp_alt(&self, other: &Self) -> Ordering {
-        let self_type = self.hand_type_p2();
-        let other_type = other.hand_type_p2();
-        if self_type > other_type {
-            return Ordering::Greater;
+    /* Non-wild cards are distinct and fit into a run of five, wild cards fill the rest */
+    fn fits_straight(&self, counts: &[u8; 13]) -> bool {
+        if counts.iter().any(|count| *count > 1) {
+            return false;
         }
-        if self_type < other_type {
-            return Ordering::Less;
+        let ranks = (0..13).filter(|rank| counts[*rank] > 0).collect::<Vec<usize>>();
+        let (Some(lowest), Some(highest)) = (ranks.first(), ranks.last()) else {
+            return true;
+        };
+        if highest - lowest <= 4 {
+            return true;
         }
+        /* Highest card below the lowest, like A2345 */
+        *highest == 12 && ranks.len() > 1 && ranks[ranks.len() - 2] <= 3
+    }
 
-        let my_iter = zip(self.cards.iter(), other.cards.iter());
-        for (my_card, other_card) in my_iter {
-            if (*my_card != CamelCard::J) && (*other_card == CamelCard::J) {
-                return Ordering::Greater;
-            }
-            if (*my_card == CamelCard::J) && (*other_card != CamelCard::J) {
-                return Ordering::Less;
+    /* Ranks in the order the tie-break looks at them */
+    fn tie_break_ranks<H: DealtCards>(&self, hand: &H, category: Option<usize>) -> Vec<i8> {
+        let mut ranks = hand.cards().iter()
+            .map(|card| self.rank(*card) as i8)
+            .collect::<Vec<i8>>();
+        if self.tie_break == TieBreak::HandOrder {
+            return ranks;
+        }
+        let straight = category.is_some_and(|idx| {
+            matches!(self.categories[idx], HandCategory::Straight | HandCategory::StraightFlush)
+        });
+        if straight && ranks.contains(&12) && ranks.contains(&0) {
+            for rank in ranks.iter_mut().filter(|rank| **rank == 12) {
+                *rank = -1;
             }
-            if my_card > other_card {
-                return Ordering::Greater;
+        }
+        let mut counts = [0u8; 14];
+        for rank in ranks.iter() {
+            counts[(*rank + 1) as usize] += 1;
+        }
+        ranks.sort_unstable_by_key(|rank| std::cmp::Reverse((counts[(*rank + 1) as usize], *rank)));
+        ranks
+    }
+
+    /* Packs a hand of at most five cards into a key that sorts like `compare`. The category
+     * (0 for none) goes above bit 20, below it 4 bits per tie-break rank. */
+    pub fn sort_key<H: DealtCards>(&self, hand: &H) -> u32 {
+        let ranks = self.rank_table();
+        let category = self.category_ranked(&ranks, hand);
+        let category_bits = category.map_or(0, |idx| idx as u32 + 1) << 20;
+        let cards = hand.cards();
+        assert!(cards.len() <= 5, "Sort keys only fit five cards");
+        let mut key = 0;
+        if self.tie_break == TieBreak::HandOrder {
+            for card in cards.iter() {
+                key = (key << 4) | (ranks[card.index()] as u32 + 1);
             }
-            if my_card < other_card {
-                return Ordering::Less;
+        } else {
+            for rank in self.tie_break_ranks(hand, category) {
+                key = (key << 4) | (rank + 1) as u32;
             }
         }
-        Ordering::Equal
+        category_bits | key << (4 * (5 - cards.len()))
     }
 
-    fn hand_type_p1(&self) -> HandType {
-        let card_counter = self.count_card_types();
-        match card_counter.len() {
-            5 => HandType::HighCard,
-            4 => HandType::OnePair,
-            3 => {//Three of a kind OR Two pair
-                match card_counter.iter().map(|(_,x)| x).max().unwrap() {
-                    3 => HandType::ThreeOfAKind,
-                    2 => HandType::TwoPair,
-                    _ => unreachable!("Unexpected match"),
-                }
-            },
-            2 => {//Four of a kind OR Full House
-                match card_counter.iter().map(|(_,x)| x).min().unwrap() {
-                    2 => HandType::FullHouse,
-                    1 => HandType::FourOfAKind,
-                    _ => unreachable!("Unexpected match"),
-                }
-            },
-            1 => HandType::FiveOfAKind,
-            _ => unreachable!("Error while counting camel cards"),
-        }
+    pub fn compare<H: DealtCards>(&self, hand: &H, other: &H) -> Ordering {
+        let (category, other_category) = (self.category(hand), self.category(other));
+        category.cmp(&other_category)
+            .then_with(|| {
+                self.tie_break_ranks(hand, category)
+                    .cmp(&self.tie_break_ranks(other, other_category))
+            })
     }
+}
 
-    /* To make more sense of this, look at above function and apply Joker rule 
-     * => CamelCard::J contribute to the best possible HandType
-     * */
-    fn hand_type_p2(&self) -> HandType {
-        let card_counter = self.count_card_types();
-        match card_counter.len() {
-            5 => {// 5 unique cards
-                if card_counter.contains_key(&CamelCard::J) {
-                    return HandType::OnePair;
-                }
-                HandType::HighCard
-            },
-            4 => {// 4 unique cards
-                if card_counter.contains_key(&CamelCard::J) {
-                    return HandType::ThreeOfAKind;
-                }
-                HandType::OnePair
-            },
-            3 => {// 3 unique cards
-                match card_counter.iter().map(|(_,x)| x).max().unwrap() {
-                    3 => {//Three-of-kind OR Four-of-a-kind with jokers
-                        if card_counter.contains_key(&CamelCard::J) {
-                            return HandType::FourOfAKind;
-                        }
-                        HandType::ThreeOfAKind
-                    },
-                    2 => {//Two pairs OR with jokers Full House or Four-of-a-kind
-                        match &card_counter.get(&CamelCard::J) {
-                            Some(val) => {
-                                if **val == 1 {
-                                    return HandType::FullHouse;
-                                }
-                                if **val == 2 {
-                                    return HandType::FourOfAKind;
-                                }
-                                unreachable!("Double-check hand_type_p2");
-                            },
-                            None => HandType::TwoPair,
-                        }
-                    },
-                    _ => unreachable!("Unexpected match"),
-                }
-            },
-            2 => {// 2 unique cards
-                if card_counter.contains_key(&CamelCard::J) {
-                    return HandType::FiveOfAKind;
-                }
-                match card_counter.iter().map(|(_,x)| x).min().unwrap() {
-                    2 => HandType::FullHouse,
-                    1 => HandType::FourOfAKind,
-                    _ => unreachable!("Unexpected match"),
-                }
-            },
-            1 => HandType::FiveOfAKind,
-            _ => unreachable!("Error while counting camel cards"),
-        }
+impl PartialOrd for CamelCardsHand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    fn count_card_types(&self) -> HashMap<CamelCard,u8> {
-        let mut card_counter = HashMap::<CamelCard,u8>::new();
-        for ccard in self.cards.iter() {
-            if card_counter.contains_key(&ccard) {
-                let foo = card_counter.get_mut(&ccard).unwrap();
-                *foo += 1;
-            } else {
-                card_counter.insert(*ccard, 1);
-            }
-        }
-        card_counter
+impl Ord for CamelCardsHand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        CamelRules::PART1.compare(self, other)
     }
 }
 
@@ -212,19 +314,36 @@ fn parse_line(input: &str) -> IResult<&str,CamelCardsHand> {
 
 #[inline]
 fn parse_camel_cards(input: &str) -> IResult<&str, Vec<CamelCard>> {
-    count(
-        alt((
-                value(CamelCard::A, tag("A")),
-                value(CamelCard::K, tag("K")),
-                value(CamelCard::Q, tag("Q")),
-                value(CamelCard::J, tag("J")),
-                value(CamelCard::T, tag("T")),
-                one_of("23456789").map(|x: char| CamelCard::Num(x.to_string().parse::<u8>().unwrap())),
-                )),
-        5)
+    count(parse_camel_card, 5)
         .parse(input)
 }
 
+fn parse_camel_card(input: &str) -> IResult<&str, CamelCard> {
+    alt((
+            value(CamelCard::A, tag("A")),
+            value(CamelCard::K, tag("K")),
+            value(CamelCard::Q, tag("Q")),
+            value(CamelCard::J, tag("J")),
+            value(CamelCard::T, tag("T")),
+            one_of("23456789").map(|x: char| CamelCard::Num(x.to_string().parse::<u8>().unwrap())),
+            ))
+        .parse(input)
+}
+
+/* Exactly five cards with suits, e.g. "AH 2D 3S 4C 5D" */
+pub fn parse_poker_hand(input: &str) -> PokerHand {
+    let suited_card = || pair(parse_camel_card, one_of("CDHS"));
+    match all_consuming(pair(suited_card(), count(preceded(tag(" "), suited_card()), 4)))
+        .parse(input)
+    {
+        Ok((_, (first, rest))) => {
+            let (cards, suits) = std::iter::once(first).chain(rest).unzip();
+            PokerHand {cards, suits}
+        },
+        Err(e) => panic!("{}", e),
+    }
+}
+
 #[inline]
 fn parse_num(input: &str) -> IResult<&str, u64> {
     take_while1(char::is_numeric)
@@ -232,10 +351,10 @@ fn parse_num(input: &str) -> IResult<&str, u64> {
         .parse(input)
 }
 
-#[aoc(day7, part1)]
-pub fn solve_part1(input: &[CamelCardsHand]) -> u64 {
+/* Rank 1 is the weakest hand */
+pub fn total_winnings(input: &[CamelCardsHand], rules: &CamelRules) -> u64 {
     let mut hands = input.to_vec();
-    hands.sort_unstable();
+    hands.sort_unstable_by(|a,b| rules.compare(a, b));
     hands.into_iter()
         .enumerate()
         .map(|(i,score)| {
@@ -244,22 +363,43 @@ pub fn solve_part1(input: &[CamelCardsHand]) -> u64 {
         .sum()
 }
 
-#[aoc(day7, part2)]
-pub fn solve_part2(input: &[CamelCardsHand]) -> u64 {
-    let mut hands = input.to_vec();
-    hands.sort_unstable_by(|a,b| a.cmp_alt(&b));
-    hands.into_iter()
+/* Same as total_winnings, but every hand is classified once */
+pub fn total_winnings_packed(input: &[CamelCardsHand], rules: &CamelRules) -> u64 {
+    let mut keyed = input.iter()
+        .map(|hand| (rules.sort_key(hand), hand.bid_value))
+        .collect::<Vec<(u32,u64)>>();
+    keyed.sort_unstable_by_key(|(key, _)| *key);
+    keyed.into_iter()
         .enumerate()
-        .map(|(i,score)| {
-            (i as u64 + 1) * score.bid_value
-        })
+        .map(|(i,(_,bid))| (i as u64 + 1) * bid)
         .sum()
 }
 
+#[aoc(day7, part1)]
+pub fn solve_part1(input: &[CamelCardsHand]) -> u64 {
+    total_winnings(input, &CamelRules::PART1)
+}
+
+#[aoc(day7, part2)]
+pub fn solve_part2(input: &[CamelCardsHand]) -> u64 {
+    total_winnings(input, &CamelRules::PART2)
+}
+
+#[aoc(day7, part1, Packed)]
+pub fn solve_part1_packed(input: &[CamelCardsHand]) -> u64 {
+    total_winnings_packed(input, &CamelRules::PART1)
+}
+
+#[aoc(day7, part2, Packed)]
+pub fn solve_part2_packed(input: &[CamelCardsHand]) -> u64 {
+    total_winnings_packed(input, &CamelRules::PART2)
+}
+
 #[cfg(test)]
 mod tests {
     use std::cmp::Ordering;
     use super::*;
+    use crate::test_util::TestRng;
 
 #[test]
     fn day7_camel_card() {
@@ -408,4 +548,91 @@ QQQJA 483";
         assert_eq!(ans,5905);
     }
 
+    #[test]
+    fn day7_camel_rules_categories() {
+        let hand = |cards: &str| input_generator(&format!("{} 1", cards)).pop().unwrap();
+        assert_eq!(CamelRules::PART1.category(&hand("KTJJT")), Some(2));
+        assert_eq!(CamelRules::PART2.category(&hand("KTJJT")), Some(5));
+        assert_eq!(CamelRules::PART2.category(&hand("JJJJJ")), Some(6));
+        assert_eq!(CamelRules::PART2.compare(&hand("JKKK2"), &hand("QQQQ2")), Ordering::Less);
+        assert_eq!(CamelRules::POKER.category(&hand("23456")), Some(4));
+        assert_eq!(CamelRules::POKER.category(&hand("A2345")), Some(4));
+        assert_eq!(CamelRules::POKER.category(&hand("QKA23")), Some(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn day7_poker_hand_of_six_cards() {
+        parse_poker_hand("2H 3D 5S 9C KD AS");
+    }
+
+    #[test]
+    fn day7_poker_rules() {
+        let poker = CamelRules::POKER;
+        let ranked = [
+            "2H 3D 5S 9C KD",//high card
+            "2C 3H 4S 8C AH",
+            "2H 2D 5S 9C KD",//pair
+            "2H 2D 5S 5C KD",//two pair
+            "2H 2D 2S 9C KD",//three of a kind
+            "AH 2D 3S 4C 5D",//wheel, lowest straight
+            "2H 3D 4S 5C 6D",
+            "TH JD QS KC AD",
+            "2H 4H 6H 8H TH",//flush
+            "3H 4H 6H 8H TH",
+            "2H 2D 2S 9C 9D",//full house
+            "3H 3D 3S 2C 2D",
+            "2H 2D 2S 2C KD",//four of a kind
+            "AH 2H 3H 4H 5H",//straight flush
+            "TH JH QH KH AH",
+        ].map(parse_poker_hand);
+        for pair in ranked.windows(2) {
+            assert_eq!(poker.compare(&pair[0], &pair[1]), Ordering::Less, "{:?}", pair);
+        }
+        assert_eq!(poker.compare(&ranked[4], &parse_poker_hand("2C 2D 2H KS 9C")), Ordering::Equal);
+        assert_eq!(poker.category(&ranked[8]), Some(5));
+        assert_eq!(poker.category(&ranked[14]), Some(8));
+    }
+
+    fn generate_hands(num_hands: usize, seed: u64) -> Vec<CamelCardsHand> {
+        let mut rng = TestRng::new(seed);
+        (0..num_hands)
+            .map(|_| CamelCardsHand {
+                /* Few distinct cards, so that there are plenty of pairs and jokers */
+                cards: (0..5).map(|_| ACE_HIGH[7 + rng.below(6) as usize]).collect(),
+                bid_value: 1 + rng.below(1000),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn day7_sort_keys() {
+        let input = input_generator(TEST_INPUT);
+        assert_eq!(solve_part1_packed(&input), 6440);
+        assert_eq!(solve_part2_packed(&input), 5905);
+        let hands = generate_hands(2000, 7);
+        for rules in [CamelRules::PART1, CamelRules::PART2, CamelRules::POKER] {
+            for pair in hands.windows(2) {
+                assert_eq!(rules.sort_key(&pair[0]).cmp(&rules.sort_key(&pair[1])),
+                    rules.compare(&pair[0], &pair[1]), "{:?}", pair);
+            }
+        }
+        let wheel = parse_poker_hand("AH 2D 3S 4C 5D");
+        let six_high = parse_poker_hand("2H 3D 4S 5C 6D");
+        assert!(CamelRules::POKER.sort_key(&wheel) < CamelRules::POKER.sort_key(&six_high));
+    }
+
+    #[test]
+    #[ignore]
+    fn day7_bench_sort_keys() {
+        let input = generate_hands(1_000_000, 2023);
+        let start = std::time::Instant::now();
+        let ans = (solve_part1(&input), solve_part2(&input));
+        let t_compare = start.elapsed();
+        let start = std::time::Instant::now();
+        let ans_packed = (solve_part1_packed(&input), solve_part2_packed(&input));
+        let t_packed = start.elapsed();
+        println!("Comparator: {:?}, packed keys: {:?}", t_compare, t_packed);
+        assert_eq!(ans, ans_packed);
+    }
 }
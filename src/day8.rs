@@ -1,6 +1,8 @@
 use core::panic;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display};
 use num::integer::lcm;
+use crate::cycle::{brent, Cycle, HashedHistory};
 use nom::{
     Parser,
     IResult,
@@ -17,21 +19,35 @@ enum Direction {
     Right,
 }
 
-#[derive(Debug,Hash,PartialEq,Eq)]
+#[derive(Clone,Copy,Debug,Hash,PartialEq,Eq)]
 struct Location {
     coords: (u8,u8,u8),
 }
 
-#[allow(dead_code)]
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (a, b, c) = self.coords;
+        write!(f, "{}{}{}", a as char, b as char, c as char)
+    }
+}
+
+/* One "AAA = (BBB, CCC)" line */
+type MapLine = (Location,(Location,Location));
+
+/* Nodes are numbered in input order, left[id] and right[id] are the ids of the next nodes */
+#[derive(Clone,Debug)]
 pub struct DesertMap {
     dirs: Vec<Direction>,
-    map: HashMap<Location,(Location,Location)>,
+    names: Vec<Location>,
+    ids: HashMap<Location,u16>,
+    left: Vec<u16>,
+    right: Vec<u16>,
 }
 
 #[aoc_generator(day8)]
 pub fn input_generator(input: &str) -> DesertMap {
     match all_consuming(separated_pair(parse_all_directions, tag("\n\n"), parse_map))
-        .map(|(x,y)| DesertMap { dirs: x, map: y })
+        .map(|(dirs, lines)| DesertMap::new(dirs, lines))
         .parse(input)
     {
         Ok((_, val)) => val,
@@ -39,6 +55,109 @@ pub fn input_generator(input: &str) -> DesertMap {
     }
 }
 
+impl DesertMap {
+    fn new(dirs: Vec<Direction>, lines: Vec<MapLine>) -> Self {
+        assert!(lines.len() <= u16::MAX as usize, "Too many nodes for u16 ids");
+        let names = lines.iter()
+            .map(|(loc,_)| *loc)
+            .collect::<Vec<Location>>();
+        let ids = names.iter()
+            .enumerate()
+            .map(|(id,loc)| (*loc, id as u16))
+            .collect::<HashMap<Location,u16>>();
+        let lookup = |loc: &Location| match ids.get(loc) {
+            Some(id) => *id,
+            None => panic!("Node {} is never defined", loc),
+        };
+        let left = lines.iter().map(|(_,(l,_))| lookup(l)).collect();
+        let right = lines.iter().map(|(_,(_,r))| lookup(r)).collect();
+        DesertMap {dirs, names, ids, left, right}
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn node_id(&self, name: &str) -> Option<u16> {
+        match name.as_bytes() {
+            [a, b, c] => self.ids.get(&Location {coords: (*a, *b, *c)}).copied(),
+            _ => None,
+        }
+    }
+
+    pub fn node_name(&self, id: u16) -> String {
+        self.names[id as usize].to_string()
+    }
+
+    #[inline]
+    fn is_z_node(&self, id: u16) -> bool {
+        self.names[id as usize].coords.2 == b'Z'
+    }
+
+    #[inline]
+    fn step_once(&self, id: u16, dir_idx: usize) -> u16 {
+        match self.dirs[dir_idx] {
+            Direction::Left => self.left[id as usize],
+            Direction::Right => self.right[id as usize],
+        }
+    }
+
+    /* All nodes reachable from `from` over both L and R edges, ignoring the instructions */
+    pub fn reachable_nodes(&self, from: u16) -> Vec<u16> {
+        let mut seen = vec![false; self.len()];
+        let mut queue = VecDeque::<u16>::from([from]);
+        seen[from as usize] = true;
+        while let Some(id) = queue.pop_front() {
+            for next in [self.left[id as usize], self.right[id as usize]] {
+                if !seen[next as usize] {
+                    seen[next as usize] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        (0..self.len() as u16)
+            .filter(|id| seen[*id as usize])
+            .collect()
+    }
+
+    /* ..Z nodes reachable from `from` over both L and R edges */
+    pub fn reachable_z_nodes(&self, from: u16) -> Vec<u16> {
+        self.reachable_nodes(from).into_iter()
+            .filter(|id| self.is_z_node(*id))
+            .collect()
+    }
+
+    /* ..Z nodes a ghost starting at `from` actually visits when following the instructions */
+    pub fn visited_z_nodes(&self, from: u16) -> Vec<u16> {
+        let num_dirs = self.dirs.len();
+        let walk = HashedHistory::new(&(from, 0usize), |(id, dir_idx)| {
+            (self.step_once(*id, *dir_idx), (dir_idx + 1) % num_dirs)
+        });
+        let mut visited = walk.history().iter()
+            .map(|(id,_)| *id)
+            .filter(|id| self.is_z_node(*id))
+            .collect::<Vec<u16>>();
+        visited.sort_unstable();
+        visited.dedup();
+        visited
+    }
+
+    /* Graphviz DOT with one edge per instruction, labelled L or R */
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph desert {\n");
+        for (id, name) in self.names.iter().enumerate() {
+            out += &format!("    \"{}\" -> \"{}\" [label=\"L\"];\n", name, self.names[self.left[id] as usize]);
+            out += &format!("    \"{}\" -> \"{}\" [label=\"R\"];\n", name, self.names[self.right[id] as usize]);
+        }
+        out += "}\n";
+        out
+    }
+}
+
 fn parse_all_directions(input: &str) -> IResult<&str, Vec<Direction>> {
     many1(parse_direction)(input)
 }
@@ -51,15 +170,12 @@ fn parse_direction(input: &str) -> IResult<&str, Direction> {
         .parse(input)
 }
 
-fn parse_map(input: &str) -> IResult<&str,HashMap<Location,(Location,Location)>> {
+fn parse_map(input: &str) -> IResult<&str,Vec<MapLine>> {
     separated_list1(tag("\n"), parse_location_line)
-        .map(|vec| {
-            vec.into_iter().collect::<HashMap<Location,(Location,Location)>>()
-        })
-    .parse(input)
+        .parse(input)
 }
 
-fn parse_location_line(input: &str) -> IResult<&str, (Location, (Location,Location))> {
+fn parse_location_line(input: &str) -> IResult<&str, MapLine> {
     separated_pair(
         parse_location, tag(" = "),
         delimited(
@@ -86,57 +202,162 @@ fn parse_location_letter<'a>(input: &'a str) -> IResult<&'a str, u8> {
         .parse(input)
 }
 
-fn find_cycle_len_p1(input: &DesertMap, start: &Location) -> u64 {
+fn find_cycle_len_p1(input: &DesertMap, start: u16) -> u64 {
+    let end = input.node_id("ZZZ").expect("No ZZZ node");
     let mut counter: u64 = 0;
-    let mut current_loc = start;
-    let my_dir_it = input.dirs.iter().cycle();
-    for direction in my_dir_it {
+    let mut current_id = start;
+    for dir_idx in (0..input.dirs.len()).cycle() {
         counter += 1;
-        current_loc = match direction {
-            Direction::Left => &input.map.get(current_loc).unwrap().0,
-            Direction::Right => &input.map.get(current_loc).unwrap().1,
-        };
-        if current_loc.coords == (b'Z',b'Z',b'Z') {
+        current_id = input.step_once(current_id, dir_idx);
+        if current_id == end {
             break;
         }
     }
     counter
 }
 
-#[inline]
-fn find_cycle_len_p2(input: &DesertMap, start: &Location) -> u64 {
-    let mut counter: u64 = 0;
-    let mut current_loc = start;
-    let my_dir_it = input.dirs.iter().cycle();
-    for direction in my_dir_it {
-        counter += 1;
-        current_loc = match direction {
-            Direction::Left => &input.map.get(current_loc).unwrap().0,
-            Direction::Right => &input.map.get(current_loc).unwrap().1,
-        };
-        if current_loc.coords.2 == b'Z' {
-            break;
-        }
-    }
-    counter
+/* A ghost's walk over (node, instruction index) is eventually periodic */
+fn find_ghost_cycle(input: &DesertMap, start: u16) -> Cycle {
+    let num_dirs = input.dirs.len();
+    brent(&(start, 0usize), |(id, dir_idx)| {
+        (input.step_once(*id, *dir_idx), (dir_idx + 1) % num_dirs)
+    })
 }
 
 #[aoc(day8, part1)]
 pub fn solve_part1(input: &DesertMap) -> u64 {
-    let start = &Location {coords: (b'A',b'A',b'A')};
+    let start = input.node_id("AAA").expect("No AAA node");
     let ans = find_cycle_len_p1(input, start);
     ans
 }
 
+/* Times a ghost stands on a ..Z node. Before prefix_len they're listed in prefix_hits; from then
+ * on the ghost is at a ..Z node exactly at the times t >= prefix_len with t ≡ h (mod period)
+ * for some h in cycle_hits. */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct GhostAnalysis {
+    pub prefix_len: u64,
+    pub period: u64,
+    pub prefix_hits: Vec<u64>,
+    pub cycle_hits: Vec<u64>,
+}
+
+impl GhostAnalysis {
+    fn is_hit(&self, t: u64) -> bool {
+        if t < self.prefix_len {
+            return self.prefix_hits.binary_search(&t).is_ok();
+        }
+        let offset = self.prefix_len + (t - self.prefix_len) % self.period;
+        self.cycle_hits.binary_search(&offset).is_ok()
+    }
+}
+
+fn analyse_ghost(input: &DesertMap, start: u16) -> GhostAnalysis {
+    let num_dirs = input.dirs.len();
+    let walk = HashedHistory::new(&(start, 0usize), |(id, dir_idx)| {
+        (input.step_once(*id, *dir_idx), (dir_idx + 1) % num_dirs)
+    });
+    let Cycle {prefix_len, period} = walk.cycle();
+    let (prefix_hits, cycle_hits) = walk.history().iter()
+        .enumerate()
+        .filter(|(_, (id, _))| input.is_z_node(*id))
+        .map(|(t, _)| t as u64)
+        .partition(|t| *t < prefix_len as u64);
+    GhostAnalysis {prefix_len: prefix_len as u64, period: period as u64, prefix_hits, cycle_hits}
+}
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (g, x, y) = extended_gcd(b, a.rem_euclid(b));
+    (g, y, x - (a / b) * y)
+}
+
+/* lcm(m1, m2) of two ghost periods doesn't fit into u64 */
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct CrtOverflow {
+    pub m1: u64,
+    pub m2: u64,
+}
+
+impl Display for CrtOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "lcm({}, {}) doesn't fit into u64", self.m1, self.m2)
+    }
+}
+
+/* Generalised CRT: merges x ≡ r1 (mod m1) and x ≡ r2 (mod m2) into x ≡ r (mod lcm(m1,m2)),
+ * or None if the moduli share a factor the residues disagree on */
+fn crt_merge((r1, m1): (u64, u64), (r2, m2): (u64, u64)) -> Result<Option<(u64, u64)>, CrtOverflow> {
+    let overflow = CrtOverflow {m1, m2};
+    let (r1, m1, r2, m2) = (r1 as i128, m1 as i128, r2 as i128, m2 as i128);
+    let (g, p, _) = extended_gcd(m1, m2);
+    if (r2 - r1) % g != 0 {
+        return Ok(None);
+    }
+    /* Once the lcm fits into u64 so do all the products below */
+    let modulus = u64::try_from((m1 / g) as u128 * m2 as u128).map_err(|_| overflow)? as i128;
+    let k = ((r2 - r1) / g * p).rem_euclid(m2 / g);
+    Ok(Some(((r1 + m1 * k).rem_euclid(modulus) as u64, modulus as u64)))
+}
+
+/* First time all ghosts stand on a ..Z node at once, or None if they never do */
+pub fn first_common_hit(ghosts: &[GhostAnalysis]) -> Result<Option<u64>, CrtOverflow> {
+    let Some(latest) = ghosts.iter().max_by_key(|ghost| ghost.prefix_len) else {
+        return Ok(None);
+    };
+    /* Before every ghost is in its loop, the ghost with the longest prefix has to hit */
+    if let Some(t) = latest.prefix_hits.iter()
+        .find(|t| ghosts.iter().all(|ghost| ghost.is_hit(**t)))
+    {
+        return Ok(Some(*t));
+    }
+    /* Afterwards it's one CRT system per combination of in-loop hits */
+    let mut residues = vec![(0u64, 1u64)];
+    for ghost in ghosts.iter() {
+        let mut merged = Vec::new();
+        for acc in residues.iter() {
+            for h in ghost.cycle_hits.iter() {
+                merged.extend(crt_merge(*acc, (h % ghost.period, ghost.period))?);
+            }
+        }
+        residues = merged;
+        residues.sort_unstable();
+        residues.dedup();
+    }
+    let start = latest.prefix_len;
+    Ok(residues.into_iter()
+        .map(|(r, m)| if r >= start { r } else { r + (start - r).div_ceil(m) * m })
+        .min())
+}
+
+fn ghost_starts(input: &DesertMap) -> Vec<u16> {
+    input.names.iter()
+        .enumerate()
+        .filter(|(_,x)| x.coords.2 == b'A')
+        .map(|(id,_)| id as u16)
+        .collect::<Vec<u16>>()
+}
+
 #[aoc(day8, part2)]
 pub fn solve_part2(input: &DesertMap) -> u64 {
-    let starting_points = input.map.iter()
-        .map(|(x,_)| x)
-        .filter(|x| x.coords.2 == b'A')
-        .collect::<Vec<&Location>>();
-    starting_points.iter()
-        .map(|start| find_cycle_len_p2(input, start))
-        .reduce(|acc,el| lcm(acc, el)).unwrap()
+    let ghosts = ghost_starts(input).into_iter()
+        .map(|start| analyse_ghost(input, start))
+        .collect::<Vec<GhostAnalysis>>();
+    match first_common_hit(&ghosts) {
+        Ok(Some(steps)) => steps,
+        Ok(None) => panic!("Ghosts never line up"),
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/* Shortcut that only holds if every ghost hits its ..Z node exactly once per loop, at the end
+ * of the loop */
+pub fn solve_part2_lcm(input: &DesertMap) -> u64 {
+    ghost_starts(input).iter()
+        .map(|start| find_ghost_cycle(input, *start).period as u64)
+        .reduce(lcm).unwrap()
 }
 
 #[cfg(test)]
@@ -156,8 +377,8 @@ ZZZ = (ZZZ, ZZZ)";
     fn day8_parser() {
         let input = input_generator(TEST_INPUT1);
         assert_eq!(input.dirs.len(), 2);
-        assert_eq!(input.map.len(), 7);
-        assert_eq!(input.map.contains_key(&Location {coords: (b'A',b'A',b'A')}), true);
+        assert_eq!(input.len(), 7);
+        assert_eq!(input.ids.contains_key(&Location {coords: (b'A',b'A',b'A')}), true);
     }
     
     #[test]
@@ -197,10 +418,156 @@ XXX = (XXX, XXX)";
         assert_eq!(ans, 6);
     }
 
+    #[test]
+    fn day8_ghost_cycle() {
+        let input = input_generator(TEST_INPUT3);
+        let cycle = find_ghost_cycle(&input, input.node_id("11A").unwrap());
+        assert_eq!(cycle, Cycle {prefix_len: 1, period: 2});
+        let cycle = find_ghost_cycle(&input, input.node_id("22A").unwrap());
+        assert_eq!(cycle, Cycle {prefix_len: 1, period: 6});
+    }
+
     #[test]
     fn day8_lcm() {
         assert_eq!(lcm(3, 7), 21);
         assert_eq!(lcm(3, 6), 6);
     }
 
+    #[test]
+    fn day8_solve_p2_lcm() {
+        let input = input_generator(TEST_INPUT3);
+        assert_eq!(solve_part2_lcm(&input), 6);
+    }
+
+    #[test]
+    fn day8_analyse_ghost() {
+        let input = input_generator(TEST_INPUT3);
+        let ghost = analyse_ghost(&input, input.node_id("22A").unwrap());
+        assert_eq!(ghost, GhostAnalysis {prefix_len: 1, period: 6, prefix_hits: vec![], cycle_hits: vec![3, 6]});
+    }
+
+    /* Z hits in the middle of the loops: hits at 2 mod 4 and 1 mod 3 */
+    const TEST_INPUT_OFFSET: &str = "L
+
+11A = (11B, XXX)
+11B = (11Z, XXX)
+11Z = (11C, XXX)
+11C = (11D, XXX)
+11D = (11B, XXX)
+22A = (22Z, XXX)
+22Z = (22B, XXX)
+22B = (22C, XXX)
+22C = (22Z, XXX)
+XXX = (XXX, XXX)";
+
+    #[test]
+    fn day8_crt_offset_hits() {
+        let input = input_generator(TEST_INPUT_OFFSET);
+        assert_eq!(solve_part2(&input), 10);
+        assert_ne!(solve_part2_lcm(&input), 10);
+    }
+
+    /* Two Z nodes in one loop: hits at odd times and at 2 mod 3 */
+    const TEST_INPUT_MULTI: &str = "L
+
+11A = (11Z, XXX)
+11Z = (11B, XXX)
+11B = (12Z, XXX)
+12Z = (11C, XXX)
+11C = (11Z, XXX)
+22A = (22B, XXX)
+22B = (22Z, XXX)
+22Z = (22A, XXX)
+XXX = (XXX, XXX)";
+
+    #[test]
+    fn day8_crt_multiple_hits_per_cycle() {
+        let input = input_generator(TEST_INPUT_MULTI);
+        assert_eq!(solve_part2(&input), 5);
+        assert_ne!(solve_part2_lcm(&input), 5);
+    }
+
+    #[test]
+    fn day8_crt_prefix_hit() {
+        /* First ghost is only ever on a Z node right after the start */
+        const INPUT: &str = "L
+
+11A = (11Z, XXX)
+11Z = (XXX, XXX)
+22A = (22Z, XXX)
+22Z = (22B, XXX)
+22B = (22Z, XXX)
+XXX = (XXX, XXX)";
+        let input = input_generator(INPUT);
+        assert_eq!(solve_part2(&input), 1);
+        assert_ne!(solve_part2_lcm(&input), 1);
+    }
+
+    #[test]
+    fn day8_crt_never() {
+        let even = GhostAnalysis {prefix_len: 0, period: 2, prefix_hits: vec![], cycle_hits: vec![0]};
+        let odd = GhostAnalysis {prefix_len: 0, period: 4, prefix_hits: vec![], cycle_hits: vec![1, 3]};
+        assert_eq!(first_common_hit(&[even.clone(), odd]), Ok(None));
+        let only_once = GhostAnalysis {prefix_len: 5, period: 1, prefix_hits: vec![3], cycle_hits: vec![]};
+        assert_eq!(first_common_hit(&[even.clone(), only_once]), Ok(None));
+        let late = GhostAnalysis {prefix_len: 7, period: 3, prefix_hits: vec![], cycle_hits: vec![8]};
+        assert_eq!(first_common_hit(&[even, late]), Ok(Some(8)));
+    }
+
+    #[test]
+    fn day8_crt_merge() {
+        assert_eq!(crt_merge((2, 4), (1, 3)), Ok(Some((10, 12))));
+        assert_eq!(crt_merge((1, 4), (3, 6)), Ok(Some((9, 12))));
+        assert_eq!(crt_merge((0, 2), (1, 4)), Ok(None));
+        /* Coprime periods whose product is past u64::MAX */
+        let (m1, m2) = (u64::MAX, u64::MAX - 1);
+        assert_eq!(crt_merge((1, m1), (2, m2)), Err(CrtOverflow {m1, m2}));
+    }
+
+    #[test]
+    fn day8_indexed_map() {
+        let input = input_generator(TEST_INPUT2);
+        assert_eq!(input.node_id("AAA"), Some(0));
+        assert_eq!(input.node_id("ZZZ"), Some(2));
+        assert_eq!(input.node_id("CCC"), None);
+        assert_eq!(input.node_name(1), "BBB");
+        assert_eq!(input.left, vec![1, 0, 2]);
+        assert_eq!(input.right, vec![1, 2, 2]);
+    }
+
+    #[test]
+    fn day8_reachability() {
+        let input = input_generator(TEST_INPUT3);
+        let names = |input: &DesertMap, ids: Vec<u16>| {
+            ids.into_iter().map(|id| input.node_name(id)).collect::<Vec<String>>()
+        };
+        let start_11 = input.node_id("11A").unwrap();
+        let start_22 = input.node_id("22A").unwrap();
+        assert_eq!(names(&input, input.reachable_nodes(start_11)), vec!["11A", "11B", "11Z", "XXX"]);
+        assert_eq!(names(&input, input.reachable_z_nodes(start_11)), vec!["11Z"]);
+        assert_eq!(names(&input, input.reachable_z_nodes(start_22)), vec!["22Z"]);
+        assert_eq!(names(&input, input.reachable_z_nodes(input.node_id("XXX").unwrap())), Vec::<String>::new());
+        assert_eq!(names(&input, input.visited_z_nodes(start_22)), vec!["22Z"]);
+        /* BBB can reach ZZZ, but only ever going left never gets there */
+        let input = input_generator("L\n\nAAA = (BBB, BBB)\nBBB = (AAA, ZZZ)\nZZZ = (ZZZ, ZZZ)");
+        assert_eq!(names(&input, input.reachable_z_nodes(0)), vec!["ZZZ"]);
+        assert_eq!(names(&input, input.visited_z_nodes(0)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn day8_dot_export() {
+        let input = input_generator(TEST_INPUT2);
+        assert_eq!(input.to_dot(),
+"digraph desert {
+    \"AAA\" -> \"BBB\" [label=\"L\"];
+    \"AAA\" -> \"BBB\" [label=\"R\"];
+    \"BBB\" -> \"AAA\" [label=\"L\"];
+    \"BBB\" -> \"ZZZ\" [label=\"R\"];
+    \"ZZZ\" -> \"ZZZ\" [label=\"L\"];
+    \"ZZZ\" -> \"ZZZ\" [label=\"R\"];
+}
+");
+        let ghosts = input_generator(TEST_INPUT3).to_dot();
+        assert!(ghosts.contains("    \"11A\" -> \"11B\" [label=\"L\"];\n"));
+    }
 }
@@ -30,5 +30,9 @@ pub mod day23;
 pub mod f128_matrix_math;
 pub mod day24;
 pub mod day25;
+pub mod cycle;
+pub mod nonogram;
+#[cfg(test)]
+mod test_util;
 
 aoc_lib! {year = 2023}
@@ -0,0 +1,226 @@
+use std::fmt;
+use crate::day12::SpringConditionRecord;
+
+/* Every row and column of a nonogram is a day 12 record: the clues are the hints and unknown
+ * cells are '?' springs. */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Nonogram {
+    rows: Vec<Vec<u64>>,
+    cols: Vec<Vec<u64>>,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Picture {
+    cells: Vec<Vec<bool>>,
+}
+
+/* Number of solutions and the first one found, None if there are no solutions */
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct NonogramSolutions {
+    pub count: u64,
+    pub picture: Option<Picture>,
+}
+
+impl Picture {
+    pub fn is_filled(&self, row: usize, col: usize) -> bool {
+        self.cells[row][col]
+    }
+
+    /* Clues that describe this picture */
+    pub fn clues(&self) -> Nonogram {
+        let line_clues = |line: &mut dyn Iterator<Item = bool>| {
+            let mut clues = Vec::<u64>::new();
+            let mut run = 0;
+            for filled in line.chain(std::iter::once(false)) {
+                if filled {
+                    run += 1;
+                } else if run > 0 {
+                    clues.push(run);
+                    run = 0;
+                }
+            }
+            clues
+        };
+        let width = self.cells.first().map_or(0, |row| row.len());
+        Nonogram {
+            rows: self.cells.iter()
+                .map(|row| line_clues(&mut row.iter().copied()))
+                .collect(),
+            cols: (0..width)
+                .map(|col| line_clues(&mut self.cells.iter().map(|row| row[col])))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Picture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, row) in self.cells.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            for filled in row.iter() {
+                write!(f, "{}", if *filled { '#' } else { '.' })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Nonogram {
+    pub fn new(rows: Vec<Vec<u64>>, cols: Vec<Vec<u64>>) -> Self {
+        Nonogram {rows, cols}
+    }
+
+    pub fn rows(&self) -> &[Vec<u64>] {
+        &self.rows
+    }
+
+    pub fn cols(&self) -> &[Vec<u64>] {
+        &self.cols
+    }
+
+    /* Line by line propagation, backtracking over the arrangements of the most constrained row
+     * once propagation gets stuck. Counts every solution. */
+    pub fn solve(&self) -> NonogramSolutions {
+        let mut out = NonogramSolutions {count: 0, picture: None};
+        let mut grid = vec![vec![b'?'; self.cols.len()]; self.rows.len()];
+        if self.propagate(&mut grid) {
+            self.backtrack(grid, &mut out);
+        }
+        out
+    }
+
+    fn backtrack(&self, grid: Vec<Vec<u8>>, out: &mut NonogramSolutions) {
+        let branch_row = grid.iter()
+            .enumerate()
+            .filter(|(_, row)| row.contains(&b'?'))
+            .map(|(idx, row)| (line_record(row, &self.rows[idx]).count_arrangements(), idx))
+            .min();
+        let Some((_, row_idx)) = branch_row else {
+            out.count += 1;
+            if out.picture.is_none() {
+                out.picture = Some(Picture {
+                    cells: grid.iter()
+                        .map(|row| row.iter().map(|cell| *cell == b'#').collect())
+                        .collect(),
+                });
+            }
+            return;
+        };
+        for arrangement in line_record(&grid[row_idx], &self.rows[row_idx]).arrangements() {
+            let mut next = grid.clone();
+            next[row_idx] = arrangement.into_bytes();
+            if self.propagate(&mut next) {
+                self.backtrack(next, out);
+            }
+        }
+    }
+
+    /* Settles lines until nothing changes. Returns false on a contradiction. */
+    fn propagate(&self, grid: &mut [Vec<u8>]) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (row, hints) in grid.iter_mut().zip(self.rows.iter()) {
+                match settle_line(row, hints) {
+                    Some(settled) => changed |= settled,
+                    None => return false,
+                }
+            }
+            for (col_idx, hints) in self.cols.iter().enumerate() {
+                let mut col = grid.iter().map(|row| row[col_idx]).collect::<Vec<u8>>();
+                match settle_line(&mut col, hints) {
+                    Some(true) => {
+                        changed = true;
+                        for (row, cell) in grid.iter_mut().zip(col) {
+                            row[col_idx] = cell;
+                        }
+                    },
+                    Some(false) => {},
+                    None => return false,
+                }
+            }
+        }
+        true
+    }
+}
+
+fn line_record(line: &[u8], hints: &[u64]) -> SpringConditionRecord {
+    SpringConditionRecord::new(std::str::from_utf8(line).unwrap(), hints)
+}
+
+/* Fixes every unknown cell that is filled in all or in none of the line's arrangements.
+ * Returns whether a cell was fixed, None if the line has no arrangements. */
+fn settle_line(line: &mut [u8], hints: &[u64]) -> Option<bool> {
+    let total = line_record(line, hints).count_arrangements();
+    if total == 0 {
+        return None;
+    }
+    let mut changed = false;
+    for idx in 0..line.len() {
+        if line[idx] != b'?' {
+            continue;
+        }
+        line[idx] = b'#';
+        let filled = line_record(line, hints).count_arrangements();
+        line[idx] = match filled {
+            0 => b'.',
+            _ if filled == total => b'#',
+            _ => b'?',
+        };
+        changed |= line[idx] != b'?';
+    }
+    Some(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestRng;
+
+    #[test]
+    fn nonogram_unique() {
+        /* A small boat */
+        let nonogram = Nonogram::new(
+            vec![vec![1], vec![2], vec![1], vec![5], vec![3]],
+            vec![vec![1], vec![2], vec![5], vec![1,2], vec![1]],
+        );
+        let solutions = nonogram.solve();
+        assert_eq!(solutions.count, 1);
+        assert_eq!(solutions.picture.unwrap().to_string(),
+"..#..
+..##.
+..#..
+#####
+.###.");
+    }
+
+    #[test]
+    fn nonogram_multiple_and_none() {
+        let diagonal = Nonogram::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]);
+        let solutions = diagonal.solve();
+        assert_eq!(solutions.count, 2);
+        assert_eq!(solutions.picture.unwrap().clues(), diagonal);
+        let impossible = Nonogram::new(vec![vec![2]], vec![vec![1], vec![]]);
+        assert_eq!(impossible.solve(), NonogramSolutions {count: 0, picture: None});
+    }
+
+    /* Clues of random pictures always have a solution with the same clues */
+    #[test]
+    fn nonogram_random_pictures() {
+        let mut rng = TestRng::new(0x9);
+        for _ in 0..50 {
+            let (height, width) = (1 + rng.below(8) as usize, 1 + rng.below(8) as usize);
+            let picture = Picture {
+                cells: (0..height)
+                    .map(|_| (0..width).map(|_| rng.below(2) == 0).collect())
+                    .collect(),
+            };
+            let nonogram = picture.clues();
+            let solutions = nonogram.solve();
+            assert!(solutions.count >= 1);
+            assert_eq!(solutions.picture.unwrap().clues(), nonogram);
+        }
+    }
+}
@@ -0,0 +1,17 @@
+/* Deterministic pseudo-random numbers for generated test inputs, a 64-bit LCG (Knuth's MMIX
+ * constants) of which only the better mixed upper bits are used */
+pub struct TestRng {
+    state: u64,
+}
+
+impl TestRng {
+    pub fn new(seed: u64) -> Self {
+        TestRng {state: seed}
+    }
+
+    /* In 0..modulus */
+    pub fn below(&mut self, modulus: u64) -> u64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.state >> 33) % modulus
+    }
+}